@@ -0,0 +1,60 @@
+// Forward the console output and errors of the page to the terminal running `bevy run web`.
+// This script is injected by the Bevy CLI when serving the app.
+(function () {
+  // Send the log entries one after another, to keep them in order
+  let queue = Promise.resolve();
+
+  function send(level, message) {
+    queue = queue
+      .then(() =>
+        fetch("/_bevy_cli/log", {
+          method: "POST",
+          headers: { "Content-Type": "application/json" },
+          body: JSON.stringify({ level, message }),
+        })
+      )
+      .catch(() => {
+        // The server might have been stopped, don't log the error to avoid an infinite loop
+      });
+  }
+
+  function stringify(value) {
+    if (typeof value === "string") {
+      return value;
+    }
+    if (value instanceof Error) {
+      return value.stack ?? String(value);
+    }
+    try {
+      return JSON.stringify(value) ?? String(value);
+    } catch {
+      return String(value);
+    }
+  }
+
+  function format(args) {
+    // Styling directives like `%c` are used for colors in the browser,
+    // remove them together with their CSS argument
+    if (typeof args[0] === "string" && args[0].includes("%c")) {
+      const styleCount = args[0].split("%c").length - 1;
+      args = [args[0].replaceAll("%c", ""), ...args.slice(1 + styleCount)];
+    }
+    return args.map(stringify).join(" ");
+  }
+
+  for (const level of ["debug", "log", "info", "warn", "error"]) {
+    const original = console[level];
+    console[level] = (...args) => {
+      original.apply(console, args);
+      send(level, format(args));
+    };
+  }
+
+  window.addEventListener("error", (event) => {
+    send("error", event.error ? stringify(event.error) : event.message);
+  });
+
+  window.addEventListener("unhandledrejection", (event) => {
+    send("error", `Unhandled promise rejection: ${stringify(event.reason)}`);
+  });
+})();
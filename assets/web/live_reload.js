@@ -0,0 +1,6 @@
+// Reload the page when the app has been rebuilt.
+// This script is injected by the Bevy CLI when running the app with `--watch`.
+(function () {
+  const events = new EventSource("/_bevy_cli/reload");
+  events.addEventListener("reload", () => location.reload());
+})();
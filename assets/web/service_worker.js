@@ -0,0 +1,45 @@
+// Service worker making the app available offline, generated by the Bevy CLI
+// All files of the bundle are cached when the service worker is installed
+// A new version of the app uses a new cache, the old one is removed once it's activated
+const CACHE_NAME = "{{ cache_name }}";
+const PRECACHE_URLS = {{ precache_urls }};
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(
+    caches
+      .open(CACHE_NAME)
+      .then((cache) => cache.addAll(PRECACHE_URLS))
+      .then(() => self.skipWaiting())
+  );
+});
+
+self.addEventListener("activate", (event) => {
+  event.waitUntil(
+    caches
+      .keys()
+      .then((cacheNames) =>
+        Promise.all(
+          cacheNames
+            .filter((cacheName) => cacheName.startsWith("bevy-app-") && cacheName !== CACHE_NAME)
+            .map((cacheName) => caches.delete(cacheName))
+        )
+      )
+      .then(() => self.clients.claim())
+  );
+});
+
+self.addEventListener("fetch", (event) => {
+  if (event.request.method !== "GET") {
+    return;
+  }
+
+  // The query string contains the app arguments, the page itself is the same
+  const url = new URL(event.request.url);
+  const cacheKey = event.request.mode === "navigate" ? url.origin + url.pathname : event.request;
+
+  event.respondWith(
+    caches
+      .match(cacheKey, { cacheName: CACHE_NAME })
+      .then((response) => response || fetch(event.request))
+  );
+});
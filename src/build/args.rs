@@ -1,6 +1,21 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
 use clap::{ArgAction, Args, Subcommand};
 
-use crate::external_cli::{arg_builder::ArgBuilder, cargo::build::CargoBuildArgs};
+use crate::{
+    config::CliConfig,
+    external_cli::{
+        arg_builder::ArgBuilder,
+        cargo::build::CargoBuildArgs,
+        wasm_bindgen::{BindgenOptions, BindgenTarget},
+    },
+    web::{
+        backend::Backend,
+        bundle::PackOptions,
+        pwa::PwaOptions,
+        wasm_opt::{OptimizationLevel, WasmOptOptions},
+    },
+};
 
 #[derive(Debug, Args)]
 pub struct BuildArgs {
@@ -12,6 +27,39 @@ pub struct BuildArgs {
     #[arg(long = "yes", default_value_t = false)]
     pub skip_prompts: bool,
 
+    /// Bundle the native binary with its assets, licenses and a desktop entry into a single
+    /// folder. Only supported for Linux.
+    #[arg(
+        short = 'b',
+        long = "bundle",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub create_native_bundle: Option<bool>,
+
+    /// Pack the native bundle into a `.tar.gz` archive, implies `--bundle`.
+    #[arg(
+        long = "tar",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub create_tar: Option<bool>,
+
+    /// The icon of the desktop entry in the native bundle.
+    ///
+    /// Ideally a square PNG file with at least 256x256 pixels.
+    #[arg(long = "icon", value_name = "PATH")]
+    pub icon: Option<PathBuf>,
+
+    /// The assets folder, relative to `BEVY_ASSET_ROOT` or the package directory, from the
+    /// configuration.
+    #[clap(skip)]
+    pub assets_path: Option<PathBuf>,
+
     /// Arguments to forward to `cargo build`.
     #[clap(flatten)]
     pub cargo_args: CargoBuildArgs,
@@ -24,7 +72,6 @@ impl BuildArgs {
     }
 
     /// Whether to build with optimizations.
-    #[cfg(feature = "wasm-opt")]
     pub(crate) fn is_release(&self) -> bool {
         self.cargo_args.compilation_args.is_release
     }
@@ -39,10 +86,62 @@ impl BuildArgs {
         self.cargo_args.compilation_args.target(self.is_web())
     }
 
+    /// Whether to bundle the native app into a single folder.
+    pub(crate) fn is_native_bundle(&self) -> bool {
+        !self.is_web()
+            && (self.create_native_bundle.unwrap_or_default()
+                || self.create_tar.unwrap_or_default())
+    }
+
     /// Generate arguments to forward to `cargo build`.
     pub(crate) fn cargo_args_builder(&self) -> ArgBuilder {
         self.cargo_args.args_builder(self.is_web())
     }
+
+    /// Apply the configuration from the manifest.
+    ///
+    /// Arguments passed on the command line take precedence over the configuration.
+    pub(crate) fn apply_config(&mut self, config: &CliConfig) {
+        self.cargo_args.feature_args.apply_config(config);
+        self.cargo_args.compilation_args.apply_config(config);
+
+        if !self.is_web() {
+            self.create_native_bundle = self.create_native_bundle.or(config.bundle);
+            self.create_tar = self.create_tar.or(config.tar);
+            self.icon = self.icon.take().or_else(|| config.icon.clone());
+            self.assets_path.clone_from(&config.assets_path);
+        }
+
+        if let Some(BuildSubcommands::Web(web_args)) = &mut self.subcommand {
+            web_args.create_packed_bundle = web_args.create_packed_bundle.or(config.bundle);
+            web_args.precompress = web_args.precompress.or(config.precompress);
+            web_args.hash_file_names = web_args.hash_file_names.or(config.hash);
+            web_args.hash_assets = web_args.hash_assets.or(config.hash_assets);
+            web_args.pwa = web_args.pwa.or(config.pwa);
+            web_args.pwa_icon = web_args.pwa_icon.take().or_else(|| config.pwa_icon.clone());
+            web_args.pwa_theme_color = web_args
+                .pwa_theme_color
+                .take()
+                .or_else(|| config.pwa_theme_color.clone());
+            web_args.create_zip = web_args.create_zip.or(config.zip);
+            web_args.backend = web_args.backend.or(config.backend);
+            web_args.bindgen_target = web_args.bindgen_target.or(config.bindgen_target);
+            web_args.typescript = web_args.typescript.or(config.typescript);
+            web_args.bindgen_debug = web_args.bindgen_debug.or(config.bindgen_debug);
+            web_args.keep_debug = web_args.keep_debug.or(config.keep_debug);
+            web_args.weak_refs = web_args.weak_refs.or(config.weak_refs);
+            web_args.reference_types = web_args.reference_types.or(config.reference_types);
+            web_args.wasm_opt_level = web_args.wasm_opt_level.or(config.wasm_opt_level);
+            // The arguments of the configuration come first, so that they can be extended
+            web_args
+                .wasm_opt_args
+                .splice(0..0, config.wasm_opt_args.iter().cloned());
+            web_args.is_multithreaded = web_args.is_multithreaded.or(config.threads);
+            web_args.template_values.clone_from(&config.template_values);
+            web_args.assets_path.clone_from(&config.assets_path);
+            web_args.web_path.clone_from(&config.web_path);
+        }
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -54,6 +153,234 @@ pub enum BuildSubcommands {
 #[derive(Debug, Args)]
 pub struct BuildWebArgs {
     // Bundle all web artifacts into a single folder.
-    #[arg(short = 'b', long = "bundle", action = ArgAction::SetTrue, default_value_t = false)]
-    pub create_packed_bundle: bool,
+    #[arg(
+        short = 'b',
+        long = "bundle",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub create_packed_bundle: Option<bool>,
+
+    /// Build all examples of the package, with a page linking to each of them.
+    ///
+    /// Implies `--bundle`. Each example is served at `{example}.html` of the bundle.
+    #[arg(long = "examples", action = ArgAction::SetTrue, default_value_t = false)]
+    pub examples: bool,
+
+    /// Create gzip and Brotli compressed copies of the bundled files, implies `--bundle`.
+    #[arg(
+        long = "precompress",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub precompress: Option<bool>,
+
+    /// Add content hashes to the file names of the build artifacts, implies `--bundle`.
+    ///
+    /// A manifest of the renamed files is written to `asset-manifest.json`.
+    #[arg(
+        long = "hash",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub hash_file_names: Option<bool>,
+
+    /// Add copies of the assets with content hashes in their file names, implies `--hash`.
+    #[arg(
+        long = "hash-assets",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub hash_assets: Option<bool>,
+
+    /// Turn the bundle into a progressive web app, which can be installed and works offline.
+    ///
+    /// Implies `--bundle`. Requires an icon, see `--pwa-icon`.
+    #[arg(
+        long = "pwa",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub pwa: Option<bool>,
+
+    /// The image to create the icons of the progressive web app from.
+    ///
+    /// Ideally a square PNG file with at least 512x512 pixels.
+    #[arg(long = "pwa-icon", value_name = "PATH")]
+    pub pwa_icon: Option<PathBuf>,
+
+    /// The theme color of the progressive web app, e.g. `#2b2c2f`.
+    #[arg(long = "pwa-theme-color", value_name = "COLOR")]
+    pub pwa_theme_color: Option<String>,
+
+    /// Pack the bundle into a zip archive, e.g. to upload it to itch.io. Implies `--bundle`.
+    #[arg(
+        long = "zip",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub create_zip: Option<bool>,
+
+    /// Print a breakdown of the Wasm binary size by sections, crates, functions and data.
+    #[arg(long = "size-report", action = ArgAction::SetTrue, default_value_t = false)]
+    pub size_report: bool,
+
+    /// Write the full size breakdown to the given JSON file, implies `--size-report`.
+    #[arg(long = "size-report-json", value_name = "PATH")]
+    pub size_report_json: Option<PathBuf>,
+
+    /// The rendering backend to build the app for.
+    ///
+    /// Activates the `webgpu` or `webgl2` feature of the package, which should enable the
+    /// corresponding Bevy feature. With `both`, the app is built twice and `index.html` loads the
+    /// WebGPU build if the browser supports it.
+    #[arg(long = "backend", value_name = "BACKEND")]
+    pub backend: Option<Backend>,
+
+    /// The kind of JavaScript bindings to create [default: web]
+    ///
+    /// With `bundler`, no `index.html` is created, as the bindings are loaded by the bundler of
+    /// the website embedding the app.
+    #[arg(long = "bindgen-target", value_name = "TARGET")]
+    pub bindgen_target: Option<BindgenTarget>,
+
+    /// Create TypeScript declarations for the JavaScript bindings.
+    #[arg(
+        long = "typescript",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub typescript: Option<bool>,
+
+    /// Include additional checks and debug information in the JavaScript bindings.
+    #[arg(
+        long = "bindgen-debug",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub bindgen_debug: Option<bool>,
+
+    /// Keep the debug sections of the Wasm binary.
+    #[arg(
+        long = "keep-debug",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub keep_debug: Option<bool>,
+
+    /// Free Rust objects automatically, using the weak references of the browser.
+    #[arg(
+        long = "weak-refs",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub weak_refs: Option<bool>,
+
+    /// Use the reference types proposal of WebAssembly in the bindings.
+    #[arg(
+        long = "reference-types",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub reference_types: Option<bool>,
+
+    /// The optimization level of wasm-opt in release builds [default: s]
+    #[arg(long = "wasm-opt-level", value_name = "LEVEL")]
+    pub wasm_opt_level: Option<OptimizationLevel>,
+
+    /// Additional arguments for wasm-opt in release builds, e.g. `--wasm-opt-arg=--enable-simd`.
+    #[arg(long = "wasm-opt-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub wasm_opt_args: Vec<String>,
+
+    /// Enable multithreading, using Wasm threads.
+    ///
+    /// This requires a nightly toolchain and serves the app with cross-origin isolation headers.
+    #[arg(
+        long = "threads",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub is_multithreaded: Option<bool>,
+
+    /// Custom values for the placeholders in `index.html`, from the configuration.
+    #[clap(skip)]
+    pub template_values: BTreeMap<String, String>,
+
+    /// The assets folder, relative to `BEVY_ASSET_ROOT` or the package directory, from the
+    /// configuration.
+    #[clap(skip)]
+    pub assets_path: Option<PathBuf>,
+
+    /// The folder with the custom `index.html`, relative to the package directory, from the
+    /// configuration.
+    #[clap(skip)]
+    pub web_path: Option<PathBuf>,
+}
+
+impl BuildWebArgs {
+    /// The options for the JavaScript bindings created by wasm-bindgen.
+    pub(crate) fn bindgen_options(&self) -> BindgenOptions {
+        BindgenOptions {
+            target: self.bindgen_target.unwrap_or_default(),
+            typescript: self.typescript.unwrap_or_default(),
+            debug: self.bindgen_debug.unwrap_or_default(),
+            keep_debug: self.keep_debug.unwrap_or_default(),
+            weak_refs: self.weak_refs.unwrap_or_default(),
+            reference_types: self.reference_types.unwrap_or_default(),
+        }
+    }
+
+    /// The options to optimize the binary with in release builds.
+    pub(crate) fn wasm_opt_options(&self) -> WasmOptOptions {
+        WasmOptOptions {
+            level: self.wasm_opt_level.unwrap_or_default(),
+            args: self.wasm_opt_args.clone(),
+        }
+    }
+
+    /// The options for packing the bundle, if it should be packed into a single folder.
+    pub(crate) fn pack_options(&self) -> Option<PackOptions> {
+        let options = PackOptions {
+            multithreaded: self.is_multithreaded.unwrap_or_default(),
+            hash_file_names: self.hash_file_names.unwrap_or_default()
+                || self.hash_assets.unwrap_or_default(),
+            hash_assets: self.hash_assets.unwrap_or_default(),
+            precompress: self.precompress.unwrap_or_default(),
+            pwa: self.pwa.unwrap_or_default().then(|| PwaOptions {
+                icon_path: self.pwa_icon.clone(),
+                theme_color: self.pwa_theme_color.clone(),
+            }),
+        };
+        let is_packed = self.create_packed_bundle.unwrap_or_default()
+            || self.create_zip.unwrap_or_default()
+            || options.pwa.is_some()
+            || options.hash_file_names
+            || options.precompress;
+
+        is_packed.then_some(options)
+    }
 }
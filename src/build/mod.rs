@@ -1,23 +1,162 @@
-use anyhow::{bail, Context as _};
-use args::{BuildArgs, BuildSubcommands};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, ensure, Context as _};
+use args::{BuildArgs, BuildSubcommands, BuildWebArgs};
 
 use crate::{
-    external_cli::{cargo, rustup, wasm_bindgen, CommandHelpers},
-    run::select_run_binary,
+    config::CliConfig,
+    external_cli::{
+        cargo::{
+            self,
+            metadata::{Metadata, Package},
+        },
+        rustup,
+        wasm_bindgen::{self, BindgenRunner, BindgenTarget},
+        CommandHelpers,
+    },
+    native,
+    run::{pick_run_target, select_run_binary, BinTarget},
     web::{
-        bundle::{create_web_bundle, PackedBundle, WebBundle},
+        archive,
+        backend::Backend,
+        bundle::{bundles_directory, create_web_bundle, PackedBundle, WebBundle},
+        fingerprint::Fingerprint,
+        folders::PackageFolders,
+        gallery,
         profiles::configure_default_web_profiles,
+        size_report::SizeReport,
+        threads, wasm_opt,
     },
 };
 
 pub mod args;
 
 pub fn build(args: &mut BuildArgs) -> anyhow::Result<()> {
+    let metadata = cargo::metadata::metadata_with_args(["--no-deps"])?;
+
+    ensure!(
+        !(args.is_web()
+            && (args.create_native_bundle.unwrap_or_default()
+                || args.create_tar.unwrap_or_default())),
+        "`--bundle` and `--tar` before `web` only apply to native builds, use `bevy build web --bundle` instead"
+    );
+
+    // Web builds and native bundles need a single binary, unless all examples are built into a
+    // gallery
+    let needs_single_target = (matches!(
+        &args.subcommand,
+        Some(BuildSubcommands::Web(web_args)) if !web_args.examples
+    ) || args.is_native_bundle())
+        && !args.cargo_args.target_args.is_examples;
+    if needs_single_target {
+        let package_selection = args.cargo_args.package_selection()?;
+        if let Some(run_target) = pick_run_target(
+            &metadata,
+            &package_selection,
+            args.cargo_args.target_args.bin.as_deref(),
+            args.cargo_args.target_args.example.as_deref(),
+            args.skip_prompts,
+        )? {
+            let target_name = Some(run_target.target.name.clone());
+            args.cargo_args.package_args.package = Some(run_target.package.name.clone());
+            if run_target.is_example {
+                args.cargo_args.target_args.example = target_name;
+            } else {
+                args.cargo_args.target_args.bin = target_name;
+            }
+        }
+    }
+
+    let package = CliConfig::select_package(
+        &metadata,
+        &args.cargo_args.package_selection()?,
+        args.cargo_args.target_args.bin.as_deref(),
+        args.cargo_args.target_args.example.as_deref(),
+    )?;
+    let config = CliConfig::for_package(&metadata, package, args.is_web())?;
+    args.apply_config(&config);
+
     if args.is_web() {
-        build_web(args)?;
+        build_web(args, &metadata)?;
     } else {
+        // Fail early if the binary to bundle is ambiguous
+        let bundled_bin_target = if args.is_native_bundle() {
+            ensure!(
+                native::bundle::is_supported_target(args.target().as_deref()),
+                "native bundles are only supported for Linux targets"
+            );
+            Some(select_run_binary(
+                &metadata,
+                &args.cargo_args.package_selection()?,
+                args.cargo_args.target_args.bin.as_deref(),
+                args.cargo_args.target_args.example.as_deref(),
+                args.target().as_deref(),
+                args.profile(),
+            )?)
+        } else {
+            None
+        };
+
         let cargo_args = args.cargo_args_builder();
-        cargo::build::command().args(cargo_args).ensure_status()?;
+        let mut command = cargo::build::command();
+        command.args(cargo_args);
+        args.cargo_args
+            .compilation_args
+            .apply_rustflags(&mut command);
+        command.ensure_status()?;
+
+        if let Some(bin_target) = bundled_bin_target {
+            bundle_native(args, &metadata, &bin_target)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Bundle the compiled native binary for distribution and pack it into an archive if requested.
+///
+/// The configuration from the manifest is expected to be applied to `args` already.
+fn bundle_native(
+    args: &BuildArgs,
+    metadata: &Metadata,
+    bin_target: &BinTarget,
+) -> anyhow::Result<()> {
+    let package = package_of(metadata, &bin_target.package_name)?;
+    let folders = PackageFolders::resolve(
+        package.manifest_path.parent().unwrap_or(Path::new("")),
+        args.assets_path.as_deref(),
+        None,
+    );
+
+    let license_files = native::bundle::license_files(package, &metadata.workspace_root)?;
+    if license_files.is_empty() {
+        println!("No license files found, the bundle won't contain any.");
+    }
+
+    let bundle_path = native::bundle::create_native_bundle(
+        &native::bundle::bundles_directory(metadata, args.profile()),
+        bin_target,
+        package.description.as_deref(),
+        &folders.assets,
+        &license_files,
+        args.icon.as_deref(),
+    )
+    .context("Failed to create native bundle")?;
+    println!("Created bundle at file://{}", bundle_path.display());
+
+    if args.create_tar.unwrap_or_default() {
+        let tar_path = native::archive::create_tar_gz(&bundle_path, &bin_target.bin_name)
+            .context("Failed to create tar.gz archive")?;
+        let size = fs::metadata(&tar_path)?.len();
+        println!(
+            "Created tar.gz archive at file://{} ({})",
+            tar_path.display(),
+            archive::format_size(size)
+        );
     }
 
     Ok(())
@@ -28,62 +167,410 @@ pub fn build(args: &mut BuildArgs) -> anyhow::Result<()> {
 /// The following steps will be performed:
 /// - Installing required tooling
 /// - Setting up default web compilation profiles
-/// - Compiling to Wasm
-/// - Optimizing the Wasm binary (in release mode)
+/// - Configuring the compilation for Wasm threads (if requested)
+/// - Compiling to Wasm, once for each rendering backend (if requested)
 /// - Creating JavaScript bindings
-/// - Creating a bundled folder (if requested)
-pub fn build_web(args: &mut BuildArgs) -> anyhow::Result<WebBundle> {
+/// - Optimizing the Wasm binary (in release mode)
+/// - Reporting the size of the Wasm binary (if requested)
+/// - Creating a bundled folder (if requested), or a gallery of all examples (with `--examples`)
+/// - Precompressing the bundled files (if requested)
+/// - Packing the bundle into a zip archive (if requested)
+///
+/// The previous bindings and optimized binary are reused if the compiled Wasm binary is unchanged.
+/// The configuration from the manifest is expected to be applied to `args` already.
+pub fn build_web(args: &mut BuildArgs, metadata: &Metadata) -> anyhow::Result<WebBundle> {
     let Some(BuildSubcommands::Web(web_args)) = &args.subcommand else {
         bail!("tried to build for the web without matching arguments");
     };
 
-    ensure_web_setup(args.skip_prompts)?;
+    let bindgen_options = web_args.bindgen_options();
+    // Only the bindings for the `web` target reference the Wasm binary by its file name
+    ensure!(
+        bindgen_options.target == BindgenTarget::Web
+            || !(web_args.hash_file_names.unwrap_or_default()
+                || web_args.hash_assets.unwrap_or_default()),
+        "`--hash` is only supported for the `web` bindgen target"
+    );
 
-    let metadata = cargo::metadata::metadata_with_args(["--no-deps"])?;
+    // With `--examples`, all examples of the package are built into a gallery
+    let is_gallery = web_args.examples || args.cargo_args.target_args.is_examples;
+    let gallery_package = if is_gallery {
+        ensure!(
+            args.cargo_args.target_args.bin.is_none()
+                && args.cargo_args.target_args.example.is_none(),
+            "`--examples` can't be combined with `--bin` or `--example`"
+        );
+        ensure!(
+            !(web_args.hash_file_names.unwrap_or_default()
+                || web_args.hash_assets.unwrap_or_default()
+                || web_args.pwa.unwrap_or_default()),
+            "`--hash` and `--pwa` are not supported with `--examples`"
+        );
+        ensure!(
+            web_args.backend != Some(Backend::Both),
+            "`--backend both` is not supported with `--examples`"
+        );
+        ensure!(
+            bindgen_options.target != BindgenTarget::Bundler,
+            "the `bundler` bindgen target is not supported with `--examples`"
+        );
+
+        let package =
+            CliConfig::select_package(metadata, &args.cargo_args.package_selection()?, None, None)?
+                .context(
+                    "Failed to determine the package of the examples, select it with `--package`",
+                )?;
+
+        // Only build the examples of the selected package
+        args.cargo_args.package_args.package = Some(package.name.clone());
+        args.cargo_args.target_args.is_examples = true;
+        Some(package)
+    } else {
+        None
+    };
+
+    let bindgen_runner = ensure_web_setup(args.skip_prompts)?;
+
+    let mut profile_args = configure_default_web_profiles(metadata)?;
+    // `--config` args are resolved from left to right,
+    // so the default configuration needs to come before the user args
+    profile_args.append(&mut args.cargo_args.common_args.config);
+    args.cargo_args.common_args.config = profile_args;
+
+    let toolchain = if web_args.is_multithreaded.unwrap_or_default() {
+        let toolchain = threads::ensure_setup(args.skip_prompts)?;
+        args.cargo_args
+            .compilation_args
+            .prepend_rustflags(threads::RUSTFLAGS.iter().copied());
+        toolchain
+    } else {
+        None
+    };
+
+    let web_bundle = match gallery_package {
+        Some(package) => {
+            build_examples(args, web_args, metadata, package, toolchain, bindgen_runner)?
+        }
+        None => build_bin(args, web_args, metadata, toolchain, bindgen_runner)?,
+    };
+
+    if let WebBundle::Packed(PackedBundle { path }) = &web_bundle {
+        println!("Created bundle at file://{}", path.display());
+
+        if web_args.create_zip.unwrap_or_default() {
+            let zip_path = archive::create_zip(path).context("Failed to create zip archive")?;
+            let size = fs::metadata(&zip_path)?.len();
+            println!(
+                "Created zip archive at file://{} ({})",
+                zip_path.display(),
+                archive::format_size(size)
+            );
+        }
+    }
+
+    Ok(web_bundle)
+}
+
+/// Build a single binary or example of the app, for each rendering backend if requested.
+fn build_bin(
+    args: &BuildArgs,
+    web_args: &BuildWebArgs,
+    metadata: &Metadata,
+    toolchain: Option<&str>,
+    bindgen_runner: BindgenRunner,
+) -> anyhow::Result<WebBundle> {
     let bin_target = select_run_binary(
-        &metadata,
-        args.cargo_args.package_args.package.as_deref(),
+        metadata,
+        &args.cargo_args.package_selection()?,
         args.cargo_args.target_args.bin.as_deref(),
         args.cargo_args.target_args.example.as_deref(),
         args.target().as_deref(),
         args.profile(),
     )?;
 
-    let mut profile_args = configure_default_web_profiles(&metadata)?;
-    // `--config` args are resolved from left to right,
-    // so the default configuration needs to come before the user args
-    profile_args.append(&mut args.cargo_args.common_args.config);
-    args.cargo_args.common_args.config = profile_args;
+    // Without a backend, the features of the app decide how it renders
+    let backend_builds = web_args.backend.map_or_else(
+        || vec![None],
+        |backend| backend.builds().into_iter().map(Some).collect(),
+    );
+    let mut webgpu_out_name = None;
 
-    let cargo_args = args.cargo_args_builder();
+    for backend_build in backend_builds {
+        compile_wasm(
+            args,
+            web_args.is_multithreaded.unwrap_or_default(),
+            backend_build.map(|backend_build| backend_build.feature),
+            toolchain,
+        )?;
 
-    println!("Compiling to WebAssembly...");
-    cargo::build::command().args(cargo_args).ensure_status()?;
+        // The builds overwrite each other's Wasm binary, so the bindings need different names
+        let suffix = backend_build.map_or("", |backend_build| backend_build.suffix);
+        let out_name = format!("{}{suffix}", bin_target.bin_name);
+        create_bindings(
+            &bin_target,
+            &out_name,
+            web_args,
+            args.is_release(),
+            bindgen_runner,
+        )?;
 
-    println!("Bundling JavaScript bindings...");
-    wasm_bindgen::bundle(&bin_target)?;
+        if web_args.size_report || web_args.size_report_json.is_some() {
+            report_size(
+                &bin_target,
+                &out_name,
+                size_report_json_path(web_args, suffix).as_deref(),
+            )?;
+        }
 
-    #[cfg(feature = "wasm-opt")]
-    if args.is_release() {
-        crate::web::wasm_opt::optimize_bin(&bin_target)?;
+        // Only the additional WebGPU build of `--backend both` has a suffix
+        if !suffix.is_empty() {
+            webgpu_out_name = Some(out_name);
+        }
     }
 
-    let web_bundle = create_web_bundle(
-        &metadata,
-        args.profile(),
+    let package = package_of(metadata, &bin_target.package_name)?;
+    create_web_bundle(
+        &bundles_directory(metadata, args.profile()),
         &bin_target,
-        web_args.create_packed_bundle,
+        &package_folders(package, web_args),
+        &web_args.template_values,
+        &web_args.bindgen_options(),
+        webgpu_out_name.as_deref(),
+        web_args.pack_options(),
     )
-    .context("Failed to create web bundle")?;
+    .context("Failed to create web bundle")
+}
 
-    if let WebBundle::Packed(PackedBundle { path }) = &web_bundle {
-        println!("Created bundle at file://{}", path.display());
+/// Build all examples of the package and bundle them into a gallery.
+///
+/// Examples which cargo skipped, e.g. because their `required-features` are not enabled, are left
+/// out of the gallery.
+fn build_examples(
+    args: &BuildArgs,
+    web_args: &BuildWebArgs,
+    metadata: &Metadata,
+    package: &Package,
+    toolchain: Option<&str>,
+    bindgen_runner: BindgenRunner,
+) -> anyhow::Result<WebBundle> {
+    let artifacts = cargo::build::build_artifacts(&mut wasm_build_command(
+        args,
+        web_args.is_multithreaded.unwrap_or_default(),
+        web_args.backend.map(|backend| backend.builds()[0].feature),
+        toolchain,
+    ))?;
+
+    let mut examples = Vec::new();
+
+    for target in package.example_targets() {
+        // Cargo only reports the examples it compiled
+        let Some(wasm_path) = artifacts
+            .iter()
+            .filter(|artifact| {
+                artifact.package_id == package.id && artifact.target.name == target.name
+            })
+            .flat_map(|artifact| &artifact.filenames)
+            .find(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "wasm")
+            })
+        else {
+            println!(
+                "Skipping example {}, because it has not been built. Are its required features enabled?",
+                target.name
+            );
+            continue;
+        };
+
+        let example = BinTarget {
+            artifact_directory: wasm_path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            bin_name: target.name.clone(),
+            package_name: package.name.clone(),
+            package_version: package.version.to_string(),
+        };
+
+        println!("Processing example {}...", example.bin_name);
+        create_bindings(
+            &example,
+            &example.bin_name,
+            web_args,
+            args.is_release(),
+            bindgen_runner,
+        )?;
+
+        if web_args.size_report || web_args.size_report_json.is_some() {
+            let suffix = format!("_{}", example.bin_name);
+            report_size(
+                &example,
+                &example.bin_name,
+                size_report_json_path(web_args, &suffix).as_deref(),
+            )?;
+        }
+
+        examples.push(example);
     }
 
-    Ok(web_bundle)
+    ensure!(
+        !examples.is_empty(),
+        "package {} has no examples which can be built for the web",
+        package.name
+    );
+
+    gallery::create_gallery(
+        &bundles_directory(metadata, args.profile()),
+        package,
+        &package_folders(package, web_args),
+        &examples,
+        &web_args.template_values,
+        &web_args.bindgen_options(),
+        &web_args.pack_options().unwrap_or_default(),
+    )
+    .context("Failed to create examples gallery")
+}
+
+/// Find the workspace member with the given name.
+fn package_of<'a>(metadata: &'a Metadata, package_name: &str) -> anyhow::Result<&'a Package> {
+    metadata
+        .packages
+        .iter()
+        .find(|package| package.name == package_name)
+        .with_context(|| format!("Failed to find package {package_name}"))
+}
+
+/// The assets and web folders of the package, taking the configured paths into account.
+fn package_folders(package: &Package, web_args: &BuildWebArgs) -> PackageFolders {
+    PackageFolders::resolve(
+        package.manifest_path.parent().unwrap_or(Path::new("")),
+        web_args.assets_path.as_deref(),
+        web_args.web_path.as_deref(),
+    )
+}
+
+/// Compile the app to Wasm, activating the feature of a rendering backend if given.
+fn compile_wasm(
+    args: &BuildArgs,
+    is_multithreaded: bool,
+    backend_feature: Option<&str>,
+    toolchain: Option<&str>,
+) -> anyhow::Result<()> {
+    wasm_build_command(args, is_multithreaded, backend_feature, toolchain).ensure_status()?;
+
+    Ok(())
+}
+
+/// The `cargo build` command compiling the app to WebAssembly.
+fn wasm_build_command(
+    args: &BuildArgs,
+    is_multithreaded: bool,
+    backend_feature: Option<&str>,
+    toolchain: Option<&str>,
+) -> Command {
+    let mut command = cargo::build::command();
+    command.args(args.cargo_args_builder());
+    if let Some(feature) = backend_feature {
+        println!("Compiling to WebAssembly ({feature})...");
+        command.args(["--features", feature]);
+    } else {
+        println!("Compiling to WebAssembly...");
+    }
+    if is_multithreaded {
+        // The standard library needs to be recompiled with the target features for threads
+        command.arg(threads::BUILD_STD_ARG);
+    }
+    if let Some(toolchain) = toolchain {
+        command.env("RUSTUP_TOOLCHAIN", toolchain);
+    }
+    args.cargo_args
+        .compilation_args
+        .apply_rustflags(&mut command);
+    command
+}
+
+/// The path of the JSON size report, with the suffix appended to the file name.
+fn size_report_json_path(web_args: &BuildWebArgs, suffix: &str) -> Option<PathBuf> {
+    web_args.size_report_json.as_ref().map(|path| {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!("{stem}{suffix}.json"))
+    })
+}
+
+/// Create the JavaScript bindings named `out_name` and optimize the Wasm binary in release mode.
+///
+/// Both steps are skipped if the compiled Wasm binary and the options didn't change.
+fn create_bindings(
+    bin_target: &BinTarget,
+    out_name: &str,
+    web_args: &BuildWebArgs,
+    optimize: bool,
+    bindgen_runner: BindgenRunner,
+) -> anyhow::Result<()> {
+    let bindgen_options = web_args.bindgen_options();
+    let wasm_opt_options = web_args.wasm_opt_options();
+    let fingerprint = Fingerprint::new(
+        bin_target,
+        out_name,
+        &[
+            (
+                "multithreaded",
+                web_args.is_multithreaded.unwrap_or_default().to_string(),
+            ),
+            ("bindgen", format!("{bindgen_options:?}")),
+            ("optimize", optimize.to_string()),
+            ("wasm-opt-level", format!("{:?}", wasm_opt_options.level)),
+            ("wasm-opt-args", format!("{:?}", wasm_opt_options.args)),
+        ],
+    )?;
+
+    if fingerprint.is_up_to_date() {
+        println!("WebAssembly binary unchanged, reusing JavaScript bindings...");
+        return Ok(());
+    }
+
+    fingerprint.invalidate()?;
+
+    println!("Bundling JavaScript bindings...");
+    wasm_bindgen::bundle(
+        bin_target,
+        out_name,
+        web_args.is_multithreaded.unwrap_or_default(),
+        &bindgen_options,
+        bindgen_runner,
+    )?;
+
+    let is_optimized = optimize && wasm_opt::optimize_bin(bin_target, out_name, &wasm_opt_options)?;
+
+    // Without wasm-opt, the next build should try to optimize the binary again
+    if is_optimized || !optimize {
+        fingerprint.save()?;
+    }
+
+    Ok(())
 }
 
-pub(crate) fn ensure_web_setup(skip_prompts: bool) -> anyhow::Result<()> {
+/// Print the size breakdown of the Wasm binary and optionally write it to a JSON file.
+fn report_size(
+    bin_target: &BinTarget,
+    out_name: &str,
+    json_path: Option<&Path>,
+) -> anyhow::Result<()> {
+    let wasm_path = bin_target
+        .artifact_directory
+        .join(format!("{out_name}_bg.wasm"));
+    let report = SizeReport::from_path(&wasm_path).context("Failed to create size report")?;
+
+    println!("Size report for {}:", wasm_path.display());
+    report.print();
+
+    if let Some(json_path) = json_path {
+        report.write_json(json_path)?;
+        println!("Wrote full size report to {}", json_path.display());
+    }
+
+    Ok(())
+}
+
+/// Install the tooling required for web builds and determine how the bindings are created.
+pub(crate) fn ensure_web_setup(skip_prompts: bool) -> anyhow::Result<BindgenRunner> {
     // The resolved dependency graph is needed to ensure the `wasm-bindgen-cli` version matches
     // exactly the `wasm-bindgen` version
     let metadata = cargo::metadata::metadata()?;
@@ -92,19 +579,23 @@ pub(crate) fn ensure_web_setup(skip_prompts: bool) -> anyhow::Result<()> {
         .packages
         .iter()
         .find(|package| package.name == "wasm-bindgen")
-        .map(|package| package.version.to_string())
+        .map(|package| package.version.clone())
         .ok_or_else(|| anyhow::anyhow!("Failed to find wasm-bindgen"))?;
 
     // `wasm32-unknown-unknown` compilation target
     rustup::install_target_if_needed("wasm32-unknown-unknown", skip_prompts)?;
-    // `wasm-bindgen-cli` for bundling
-    cargo::install::if_needed(
-        wasm_bindgen::PROGRAM,
-        wasm_bindgen::PACKAGE,
-        Some(&wasm_bindgen_version),
-        skip_prompts,
-        false,
-    )?;
 
-    Ok(())
+    let runner = BindgenRunner::for_version(&wasm_bindgen_version);
+    // `wasm-bindgen-cli` for bundling, unless the linked library can be used
+    if runner == BindgenRunner::Cli {
+        cargo::install::if_needed(
+            wasm_bindgen::PROGRAM,
+            wasm_bindgen::PACKAGE,
+            Some(&wasm_bindgen_version.to_string()),
+            skip_prompts,
+            false,
+        )?;
+    }
+
+    Ok(runner)
 }
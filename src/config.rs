@@ -0,0 +1,456 @@
+//! Project-level configuration of the CLI, defined in the Cargo manifest.
+//!
+//! The configuration is read from the `[package.metadata.bevy_cli]` and
+//! `[workspace.metadata.bevy_cli]` tables. Platform-specific values can be placed in the `native`
+//! and `web` sub-tables:
+//!
+//! ```toml
+//! [package.metadata.bevy_cli]
+//! features = ["dev"]
+//!
+//! [package.metadata.bevy_cli.web]
+//! features = ["webgpu"]
+//! rustflags = ["--cfg", "getrandom_backend=\"wasm_js\""]
+//! port = 8080
+//! open = true
+//! ```
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use serde::Deserialize;
+
+use crate::{
+    external_cli::{
+        cargo::metadata::{Metadata, Package, PackageSelection},
+        wasm_bindgen::BindgenTarget,
+    },
+    run::select_run_target,
+    web::{backend::Backend, wasm_opt::OptimizationLevel},
+};
+
+/// The configuration of the CLI for a single platform.
+///
+/// All values are optional, arguments passed on the command line take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CliConfig {
+    /// Additional features to activate.
+    pub features: Vec<String>,
+    /// Whether to activate the `default` feature.
+    pub default_features: Option<bool>,
+    /// Additional flags to pass to `rustc`.
+    pub rustflags: Vec<String>,
+    /// The profile to compile the app with, if neither `--profile` nor `--release` is used.
+    pub profile: Option<String>,
+    /// The host address to run the web server on.
+    pub host: Option<String>,
+    /// The port to run the web server on.
+    pub port: Option<u16>,
+    /// Whether to serve the app via HTTPS.
+    pub https: Option<bool>,
+    /// Whether to open the app in the browser.
+    pub open: Option<bool>,
+    /// Whether to bundle the binary or web artifacts with the assets into a single folder.
+    pub bundle: Option<bool>,
+    /// Whether to create gzip and Brotli compressed copies of the bundled files.
+    pub precompress: Option<bool>,
+    /// Whether to add content hashes to the file names of the bundled build artifacts.
+    pub hash: Option<bool>,
+    /// Whether to add copies of the bundled assets with content hashes in their file names.
+    pub hash_assets: Option<bool>,
+    /// Whether to turn the bundle into a progressive web app.
+    pub pwa: Option<bool>,
+    /// The image to create the icons of the progressive web app from, relative to the manifest
+    /// which defines it.
+    pub pwa_icon: Option<PathBuf>,
+    /// The theme color of the progressive web app.
+    pub pwa_theme_color: Option<String>,
+    /// Whether to pack the bundle into a zip archive.
+    pub zip: Option<bool>,
+    /// Whether to pack the native bundle into a `.tar.gz` archive.
+    pub tar: Option<bool>,
+    /// The icon of the desktop entry in the native bundle, relative to the manifest which defines
+    /// it.
+    pub icon: Option<PathBuf>,
+    /// The rendering backend to build web apps for.
+    pub backend: Option<Backend>,
+    /// The kind of JavaScript bindings to create.
+    pub bindgen_target: Option<BindgenTarget>,
+    /// Whether to create TypeScript declarations for the JavaScript bindings.
+    pub typescript: Option<bool>,
+    /// Whether to include debug information in the JavaScript bindings.
+    pub bindgen_debug: Option<bool>,
+    /// Whether to keep the debug sections of the Wasm binary.
+    pub keep_debug: Option<bool>,
+    /// Whether to free Rust objects using the weak references of the browser.
+    pub weak_refs: Option<bool>,
+    /// Whether to use the reference types proposal of WebAssembly.
+    pub reference_types: Option<bool>,
+    /// The optimization level of wasm-opt in release builds.
+    pub wasm_opt_level: Option<OptimizationLevel>,
+    /// Additional arguments for wasm-opt in release builds.
+    pub wasm_opt_args: Vec<String>,
+    /// Whether to enable multithreading for web builds.
+    pub threads: Option<bool>,
+    /// The assets folder, relative to `BEVY_ASSET_ROOT` or the package directory.
+    pub assets_path: Option<PathBuf>,
+    /// The folder with the custom `index.html` and other web files, relative to the package
+    /// directory.
+    pub web_path: Option<PathBuf>,
+    /// Custom values for the placeholders in `index.html`.
+    pub template_values: BTreeMap<String, String>,
+}
+
+/// The `bevy_cli` table in the manifest metadata.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ConfigTable {
+    /// Configuration which applies to all platforms.
+    #[serde(flatten)]
+    base: CliConfig,
+    /// Configuration which only applies to native builds.
+    native: CliConfig,
+    /// Configuration which only applies to web builds.
+    web: CliConfig,
+}
+
+impl CliConfig {
+    /// Determine the package whose configuration applies.
+    ///
+    /// The package is resolved independently of the binary that is built, in this order:
+    /// - The package selected with `--package`
+    /// - The package containing the binary or example selected with `--bin` or `--example`
+    /// - The package Cargo selects, see [`Metadata::selected_packages`]
+    ///
+    /// If Cargo selects multiple packages, only the workspace configuration is used.
+    /// A warning is printed in that case if any of them defines its own configuration.
+    pub(crate) fn select_package<'a>(
+        metadata: &'a Metadata,
+        package_selection: &PackageSelection,
+        bin_name: Option<&str>,
+        example_name: Option<&str>,
+    ) -> anyhow::Result<Option<&'a Package>> {
+        if package_selection.package.is_none() && (bin_name.is_some() || example_name.is_some()) {
+            // The binary can be in any member, the build reports if its package isn't selected
+            let all_members = PackageSelection {
+                is_workspace: true,
+                ..Default::default()
+            };
+            let run_target = select_run_target(metadata, &all_members, bin_name, example_name)?;
+            return Ok(Some(run_target.package));
+        }
+
+        let packages = metadata.selected_packages(package_selection)?;
+        if let [package] = packages[..] {
+            return Ok(Some(package));
+        }
+
+        if packages
+            .iter()
+            .any(|package| package.metadata.get("bevy_cli").is_some())
+        {
+            eprintln!(
+                "warning: couldn't determine which package's `[package.metadata.bevy_cli]` to use, \
+                only `[workspace.metadata.bevy_cli]` applies. Select a package with `--package` \
+                or run the command in the package's folder."
+            );
+        }
+
+        Ok(None)
+    }
+
+    /// Determine the configuration for the given package.
+    ///
+    /// The workspace configuration is used as a base, which is then overwritten by the package
+    /// configuration. In both cases, the platform-specific values take precedence.
+    pub(crate) fn for_package(
+        metadata: &Metadata,
+        package: Option<&Package>,
+        is_web: bool,
+    ) -> anyhow::Result<Self> {
+        let mut config = Self::from_metadata(&metadata.metadata, &metadata.workspace_root, is_web)
+            .context("failed to parse `[workspace.metadata.bevy_cli]`")?;
+
+        if let Some(package) = package {
+            let package_dir = package.manifest_path.parent().unwrap_or(Path::new(""));
+            let package_config = Self::from_metadata(&package.metadata, package_dir, is_web)
+                .with_context(|| {
+                    format!(
+                        "failed to parse `[package.metadata.bevy_cli]` of package {}",
+                        package.name
+                    )
+                })?;
+            config = config.overwrite(package_config);
+        }
+
+        Ok(config)
+    }
+
+    /// Parse the configuration from the `metadata` table of a package or workspace.
+    ///
+    /// The icon paths are resolved relative to `manifest_dir`, the directory of the manifest
+    /// defining the table, so they don't depend on the directory the CLI is run in.
+    fn from_metadata(
+        metadata: &serde_json::Value,
+        manifest_dir: &Path,
+        is_web: bool,
+    ) -> anyhow::Result<Self> {
+        let Some(table) = metadata.get("bevy_cli") else {
+            return Ok(Self::default());
+        };
+
+        let table = ConfigTable::deserialize(table)?;
+        let platform = if is_web { table.web } else { table.native };
+
+        let mut config = table.base.overwrite(platform);
+        for path in [&mut config.icon, &mut config.pwa_icon]
+            .into_iter()
+            .flatten()
+        {
+            *path = manifest_dir.join(&*path);
+        }
+
+        Ok(config)
+    }
+
+    /// Merge this configuration with another one, which takes precedence.
+    ///
+    /// Features, `rustc` flags and template values are combined, all other values are replaced if
+    /// they are defined in `other`.
+    fn overwrite(mut self, other: Self) -> Self {
+        for feature in other.features {
+            if !self.features.contains(&feature) {
+                self.features.push(feature);
+            }
+        }
+
+        // The order of the flags matters, so they are not deduplicated
+        self.rustflags.extend(other.rustflags);
+
+        self.wasm_opt_args.extend(other.wasm_opt_args);
+        self.template_values.extend(other.template_values);
+
+        Self {
+            features: self.features,
+            default_features: other.default_features.or(self.default_features),
+            rustflags: self.rustflags,
+            profile: other.profile.or(self.profile),
+            host: other.host.or(self.host),
+            port: other.port.or(self.port),
+            https: other.https.or(self.https),
+            open: other.open.or(self.open),
+            bundle: other.bundle.or(self.bundle),
+            precompress: other.precompress.or(self.precompress),
+            hash: other.hash.or(self.hash),
+            hash_assets: other.hash_assets.or(self.hash_assets),
+            pwa: other.pwa.or(self.pwa),
+            pwa_icon: other.pwa_icon.or(self.pwa_icon),
+            pwa_theme_color: other.pwa_theme_color.or(self.pwa_theme_color),
+            zip: other.zip.or(self.zip),
+            tar: other.tar.or(self.tar),
+            icon: other.icon.or(self.icon),
+            backend: other.backend.or(self.backend),
+            bindgen_target: other.bindgen_target.or(self.bindgen_target),
+            typescript: other.typescript.or(self.typescript),
+            bindgen_debug: other.bindgen_debug.or(self.bindgen_debug),
+            keep_debug: other.keep_debug.or(self.keep_debug),
+            weak_refs: other.weak_refs.or(self.weak_refs),
+            reference_types: other.reference_types.or(self.reference_types),
+            wasm_opt_level: other.wasm_opt_level.or(self.wasm_opt_level),
+            wasm_opt_args: self.wasm_opt_args,
+            threads: other.threads.or(self.threads),
+            assets_path: other.assets_path.or(self.assets_path),
+            web_path: other.web_path.or(self.web_path),
+            template_values: self.template_values,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::external_cli::cargo::metadata::fixture::TestWorkspace;
+
+    fn selection<'a>(package_name: Option<&'a str>, manifest_dir: &str) -> PackageSelection<'a> {
+        PackageSelection {
+            package: package_name,
+            manifest_dir: PathBuf::from(manifest_dir),
+            ..Default::default()
+        }
+    }
+
+    fn selected_package_name(
+        metadata: &Metadata,
+        package_name: Option<&str>,
+        manifest_dir: &str,
+    ) -> Option<String> {
+        CliConfig::select_package(metadata, &selection(package_name, manifest_dir), None, None)
+            .unwrap()
+            .map(|package| package.name.clone())
+    }
+
+    #[test]
+    fn should_select_package_by_name() {
+        let metadata = TestWorkspace::new()
+            .package("game", "game", &[])
+            .package("editor", "editor", &[])
+            .build();
+
+        assert_eq!(
+            selected_package_name(&metadata, Some("editor"), "/workspace/game").as_deref(),
+            Some("editor")
+        );
+        assert!(CliConfig::select_package(
+            &metadata,
+            &selection(Some("foo"), "/workspace"),
+            None,
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn should_select_package_of_binary() {
+        let metadata = TestWorkspace::new()
+            .package("game", "game", &[])
+            .package("editor", "editor", &[("bin", "editor")])
+            .build();
+
+        let package = CliConfig::select_package(
+            &metadata,
+            &selection(None, "/workspace/game"),
+            Some("editor"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(package.map(|package| package.name.as_str()), Some("editor"));
+    }
+
+    #[test]
+    fn should_select_package_in_current_dir() {
+        // Without `default-members`, Cargo selects the root package in the workspace root
+        let metadata = TestWorkspace::new()
+            .package("root", "", &[])
+            .package("game", "game", &[])
+            .package("editor", "editor", &[])
+            .default_members(&["root"])
+            .build();
+
+        assert_eq!(
+            selected_package_name(&metadata, None, "/workspace/game/src").as_deref(),
+            Some("game")
+        );
+        assert_eq!(
+            selected_package_name(&metadata, None, "/workspace").as_deref(),
+            Some("root")
+        );
+    }
+
+    #[test]
+    fn should_select_single_package() {
+        let metadata = TestWorkspace::new()
+            .package("game", "crates/game", &[])
+            .build();
+        assert_eq!(
+            selected_package_name(&metadata, None, "/workspace").as_deref(),
+            Some("game")
+        );
+
+        let metadata = TestWorkspace::new()
+            .package("game", "game", &[])
+            .package("editor", "editor", &[])
+            .build();
+        assert_eq!(selected_package_name(&metadata, None, "/workspace"), None);
+    }
+
+    #[test]
+    fn should_use_defaults_without_table() {
+        let config = CliConfig::from_metadata(&json!(null), Path::new(""), false).unwrap();
+        assert_eq!(config, CliConfig::default());
+    }
+
+    #[test]
+    fn should_prefer_platform_values() {
+        let metadata = json!({
+            "bevy_cli": {
+                "features": ["dev"],
+                "port": 4001,
+                "web": {
+                    "features": ["webgpu"],
+                    "port": 8080,
+                    "wasm-opt-level": "z",
+                },
+                "native": {
+                    "default-features": false,
+                }
+            }
+        });
+
+        let web = CliConfig::from_metadata(&metadata, Path::new(""), true).unwrap();
+        assert_eq!(web.features, ["dev", "webgpu"]);
+        assert_eq!(web.port, Some(8080));
+        assert_eq!(web.default_features, None);
+        assert_eq!(web.wasm_opt_level, Some(OptimizationLevel::Z));
+
+        let native = CliConfig::from_metadata(&metadata, Path::new(""), false).unwrap();
+        assert_eq!(native.features, ["dev"]);
+        assert_eq!(native.port, Some(4001));
+        assert_eq!(native.default_features, Some(false));
+        assert_eq!(native.wasm_opt_level, None);
+    }
+
+    #[test]
+    fn should_resolve_paths_relative_to_manifest() {
+        let metadata = json!({
+            "bevy_cli": {
+                "icon": "assets/icon.png",
+                "web": {
+                    "pwa-icon": "/images/icon.png",
+                }
+            }
+        });
+        let manifest_dir = Path::new("/workspace/crates/game");
+
+        let web = CliConfig::from_metadata(&metadata, manifest_dir, true).unwrap();
+        assert_eq!(
+            web.icon.as_deref(),
+            Some(Path::new("/workspace/crates/game/assets/icon.png"))
+        );
+        assert_eq!(web.pwa_icon.as_deref(), Some(Path::new("/images/icon.png")));
+    }
+
+    #[test]
+    fn should_overwrite_with_other_config() {
+        let base = CliConfig {
+            features: vec!["dev".to_owned()],
+            rustflags: vec!["-Cdebuginfo=0".to_owned()],
+            profile: Some("foo".to_owned()),
+            open: Some(true),
+            template_values: BTreeMap::from([
+                ("title".to_owned(), "Game".to_owned()),
+                ("theme_color".to_owned(), "#000".to_owned()),
+            ]),
+            ..Default::default()
+        };
+        let other = CliConfig {
+            features: vec!["dev".to_owned(), "webgpu".to_owned()],
+            rustflags: vec!["--cfg".to_owned(), "foo".to_owned()],
+            profile: Some("bar".to_owned()),
+            template_values: BTreeMap::from([("title".to_owned(), "Web Game".to_owned())]),
+            ..Default::default()
+        };
+
+        let config = base.overwrite(other);
+        assert_eq!(config.features, ["dev", "webgpu"]);
+        assert_eq!(config.rustflags, ["-Cdebuginfo=0", "--cfg", "foo"]);
+        assert_eq!(config.profile.as_deref(), Some("bar"));
+        assert_eq!(config.open, Some(true));
+        assert_eq!(config.template_values["title"], "Web Game");
+        assert_eq!(config.template_values["theme_color"], "#000");
+    }
+}
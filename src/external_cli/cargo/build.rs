@@ -1,10 +1,18 @@
-use std::process::Command;
+use std::{
+    io,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
 
 use clap::{ArgAction, Args};
+use serde::Deserialize;
 
 use crate::external_cli::arg_builder::ArgBuilder;
 
-use super::{program, CargoCommonArgs, CargoCompilationArgs, CargoFeatureArgs, CargoManifestArgs};
+use super::{
+    metadata::{PackageSelection, Target},
+    program, CargoCommonArgs, CargoCompilationArgs, CargoFeatureArgs, CargoManifestArgs,
+};
 
 /// Create a command to run `cargo build`.
 pub(crate) fn command() -> Command {
@@ -13,6 +21,57 @@ pub(crate) fn command() -> Command {
     command
 }
 
+/// Run the `cargo build` command and collect the artifacts Cargo reports.
+///
+/// The diagnostics are still printed like in a normal build, only the JSON messages on stdout are
+/// captured.
+pub(crate) fn build_artifacts(command: &mut Command) -> anyhow::Result<Vec<Artifact>> {
+    let output = command
+        .arg("--message-format=json-render-diagnostics")
+        .stderr(Stdio::inherit())
+        .output()?;
+    anyhow::ensure!(
+        output.status.success(),
+        "Command {} exited with status code {}",
+        command.get_program().to_string_lossy(),
+        output.status
+    );
+
+    Ok(parse_artifacts(&output.stdout))
+}
+
+/// Parse the `compiler-artifact` messages of the JSON output, ignoring all other messages.
+fn parse_artifacts(output: &[u8]) -> Vec<Artifact> {
+    serde_json::Deserializer::from_slice(output)
+        .into_iter::<Message>()
+        .filter_map(|message| match message {
+            Ok(Message::CompilerArtifact(artifact)) => Some(artifact),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A message of `cargo build --message-format=json`, see
+/// <https://doc.rust-lang.org/cargo/reference/external-tools.html#json-messages>.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum Message {
+    CompilerArtifact(Artifact),
+    #[serde(other)]
+    Other,
+}
+
+/// The files created for a compiled target.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Artifact {
+    /// The ID of the package containing the target.
+    pub package_id: String,
+    /// The compiled target.
+    pub target: Target,
+    /// The paths of the created files.
+    pub filenames: Vec<PathBuf>,
+}
+
 #[derive(Debug, Args)]
 pub struct CargoBuildArgs {
     #[clap(flatten)]
@@ -39,6 +98,18 @@ impl CargoBuildArgs {
             .append(self.compilation_args.args_builder(is_web))
             .append(self.manifest_args.args_builder())
     }
+
+    /// The packages selected with `--package`, `--workspace` and `--exclude`.
+    pub(crate) fn package_selection(&self) -> io::Result<PackageSelection<'_>> {
+        Ok(PackageSelection {
+            package: self.package_args.package.as_deref(),
+            is_workspace: self.package_args.is_workspace,
+            exclude: self.package_args.exclude.as_deref(),
+            manifest_dir: PackageSelection::manifest_dir(
+                self.manifest_args.manifest_path.as_deref(),
+            )?,
+        })
+    }
 }
 
 #[derive(Debug, Args)]
@@ -125,3 +196,27 @@ impl CargoTargetBuildArgs {
             .add_flag_if("--all-targets", self.is_all_targets)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_artifacts() {
+        let output = br#"{"reason":"compiler-artifact","package_id":"path+file:///game#0.1.0","manifest_path":"/game/Cargo.toml","target":{"kind":["example"],"crate_types":["bin"],"name":"breakout","src_path":"/game/examples/breakout.rs"},"filenames":["/game/target/wasm32-unknown-unknown/web/examples/breakout.wasm"],"executable":null,"fresh":true}
+{"reason":"compiler-message","package_id":"path+file:///game#0.1.0","message":{"rendered":"warning: unused variable"}}
+{"reason":"build-finished","success":true}
+"#;
+
+        let artifacts = parse_artifacts(output);
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].package_id, "path+file:///game#0.1.0");
+        assert_eq!(artifacts[0].target.name, "breakout");
+        assert_eq!(
+            artifacts[0].filenames,
+            [PathBuf::from(
+                "/game/target/wasm32-unknown-unknown/web/examples/breakout.wasm"
+            )]
+        );
+    }
+}
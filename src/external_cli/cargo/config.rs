@@ -0,0 +1,180 @@
+//! Reading the `rustflags` from the configuration files of Cargo, see
+//! <https://doc.rust-lang.org/cargo/reference/config.html>.
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use toml_edit::{DocumentMut, Item};
+
+/// The `rustflags` defined in the configuration files of Cargo.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct ConfiguredRustflags {
+    /// The flags of `build.rustflags`, combined from all files like Cargo does.
+    pub build: Vec<String>,
+    /// Whether a `target` table defines `rustflags`, in which case Cargo ignores
+    /// `build.rustflags`.
+    pub has_target_rustflags: bool,
+}
+
+impl ConfiguredRustflags {
+    /// Read the flags from the configuration files which apply to commands run in the current
+    /// directory.
+    ///
+    /// Files which can't be read or parsed are skipped, Cargo reports the errors itself.
+    pub(crate) fn load() -> Self {
+        let current_dir = env::current_dir().unwrap_or_default();
+        let cargo_home = home::cargo_home().ok();
+
+        let mut rustflags = Self::default();
+        for path in config_files(&current_dir, cargo_home.as_deref()) {
+            if let Some(document) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| contents.parse::<DocumentMut>().ok())
+            {
+                rustflags.merge(&document);
+            }
+        }
+
+        // Like the other environment variables of the configuration, it has the highest precedence
+        if let Ok(build_rustflags) = env::var("CARGO_BUILD_RUSTFLAGS") {
+            rustflags
+                .build
+                .extend(build_rustflags.split_whitespace().map(str::to_owned));
+        }
+
+        rustflags
+    }
+
+    /// Add the flags of a configuration file with a higher precedence than the previous ones.
+    ///
+    /// Cargo joins the arrays of all files, placing the values with higher precedence last.
+    fn merge(&mut self, document: &DocumentMut) {
+        if let Some(build_rustflags) = document
+            .get("build")
+            .and_then(|build| build.get("rustflags"))
+        {
+            self.build.extend(string_list(build_rustflags));
+        }
+
+        self.has_target_rustflags |= document
+            .get("target")
+            .and_then(Item::as_table_like)
+            .is_some_and(|targets| {
+                targets
+                    .iter()
+                    .any(|(_, target)| target.get("rustflags").is_some())
+            });
+    }
+}
+
+/// The configuration files in the order of increasing precedence.
+///
+/// Cargo reads `.cargo/config.toml` in the current directory and all of its parents, where files
+/// in deeper directories take precedence, and the one in the Cargo home directory last.
+fn config_files(current_dir: &Path, cargo_home: Option<&Path>) -> Vec<PathBuf> {
+    let mut config_dirs: Vec<_> = current_dir
+        .ancestors()
+        .map(|dir| dir.join(".cargo"))
+        .collect();
+    if let Some(cargo_home) = cargo_home {
+        if !config_dirs.iter().any(|dir| dir == cargo_home) {
+            config_dirs.push(cargo_home.to_path_buf());
+        }
+    }
+
+    config_dirs
+        .into_iter()
+        .rev()
+        .filter_map(|dir| {
+            // Cargo still supports the file without extension
+            ["config.toml", "config"]
+                .into_iter()
+                .map(|file_name| dir.join(file_name))
+                .find(|path| path.is_file())
+        })
+        .collect()
+}
+
+/// The strings of a configuration value, which is either an array or a space-separated string.
+fn string_list(item: &Item) -> Vec<String> {
+    if let Some(value) = item.as_str() {
+        value.split_whitespace().map(str::to_owned).collect()
+    } else {
+        item.as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value.as_str().map(str::to_owned))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_join_build_rustflags_of_all_files() {
+        let mut rustflags = ConfiguredRustflags::default();
+        rustflags.merge(&r#"build.rustflags = "--cfg home""#.parse().unwrap());
+        rustflags.merge(
+            &r#"
+            [build]
+            rustflags = ["--cfg", "getrandom_backend=\"wasm_js\""]
+            "#
+            .parse()
+            .unwrap(),
+        );
+
+        assert_eq!(
+            rustflags,
+            ConfiguredRustflags {
+                build: vec![
+                    "--cfg".to_owned(),
+                    "home".to_owned(),
+                    "--cfg".to_owned(),
+                    "getrandom_backend=\"wasm_js\"".to_owned(),
+                ],
+                has_target_rustflags: false,
+            }
+        );
+
+        rustflags.merge(
+            &r#"
+            [target.'cfg(target_family = "wasm")']
+            rustflags = ["-Ctarget-feature=+simd128"]
+            "#
+            .parse()
+            .unwrap(),
+        );
+        assert!(rustflags.has_target_rustflags);
+    }
+
+    #[test]
+    fn should_find_config_files_by_precedence() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace = temp_dir.path().join("workspace");
+        let package = workspace.join("crates/game");
+        let cargo_home = temp_dir.path().join("cargo_home");
+        for dir in [
+            workspace.join(".cargo"),
+            package.join(".cargo"),
+            cargo_home.clone(),
+        ] {
+            fs::create_dir_all(&dir).unwrap();
+        }
+        fs::write(workspace.join(".cargo/config.toml"), "").unwrap();
+        fs::write(package.join(".cargo/config"), "").unwrap();
+        fs::write(cargo_home.join("config.toml"), "").unwrap();
+
+        let files = config_files(&package, Some(&cargo_home));
+        assert_eq!(
+            files,
+            [
+                cargo_home.join("config.toml"),
+                workspace.join(".cargo/config.toml"),
+                package.join(".cargo/config"),
+            ]
+        );
+    }
+}
@@ -1,5 +1,11 @@
 #![expect(dead_code, reason = "Will be used for bevy bump and perhaps bevy run")]
-use std::{ffi::OsStr, path::PathBuf, process::Command};
+use std::{
+    env,
+    ffi::OsStr,
+    io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
 
 use semver::{Version, VersionReq};
 use serde::Deserialize;
@@ -28,7 +34,16 @@ where
     I: IntoIterator<Item = S>,
     S: AsRef<OsStr>,
 {
-    let output = command().args(additional_args).output()?;
+    // Errors are reported by `cargo` itself, e.g. if there is no manifest
+    let output = command()
+        .args(additional_args)
+        .stderr(Stdio::inherit())
+        .output()?;
+    anyhow::ensure!(
+        output.status.success(),
+        "Failed to obtain the Cargo metadata ({})",
+        output.status
+    );
     let metadata = serde_json::from_slice(&output.stdout)?;
     Ok(metadata)
 }
@@ -55,6 +70,93 @@ pub struct Metadata {
     /// The absolute path to the root of the workspace.
     /// This will be the root of the package if no workspace is used.
     pub workspace_root: PathBuf,
+    /// The contents of the `[workspace.metadata]` table, or `null` if it is not defined.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+impl Metadata {
+    /// The packages Cargo selects for the given package selection arguments.
+    ///
+    /// Without `--package` or `--workspace`, this is the package of the manifest in the directory
+    /// or one of its parents. If that's the root manifest of the workspace, the default members
+    /// are selected instead.
+    ///
+    /// See <https://doc.rust-lang.org/cargo/commands/cargo-build.html#package-selection>.
+    pub fn selected_packages(&self, selection: &PackageSelection) -> anyhow::Result<Vec<&Package>> {
+        if let Some(package_name) = selection.package {
+            let package = self
+                .packages
+                .iter()
+                .find(|package| package.name == package_name)
+                .ok_or_else(|| anyhow::anyhow!("Failed to find package {package_name}"))?;
+            return Ok(vec![package]);
+        }
+
+        if selection.is_workspace {
+            return Ok(self
+                .members(&self.workspace_members)
+                .filter(|package| Some(package.name.as_str()) != selection.exclude)
+                .collect());
+        }
+
+        // Packages can be nested, so the innermost one containing the directory is used
+        let current_package = self
+            .members(&self.workspace_members)
+            .filter_map(|package| {
+                let package_dir = package.manifest_path.parent()?;
+                selection
+                    .manifest_dir
+                    .starts_with(package_dir)
+                    .then_some((package, package_dir.components().count()))
+            })
+            .max_by_key(|(_, depth)| *depth)
+            .map(|(package, _)| package);
+
+        match current_package {
+            Some(package) if package.manifest_path.parent() != Some(&self.workspace_root) => {
+                Ok(vec![package])
+            }
+            _ => Ok(self.members(&self.workspace_default_members).collect()),
+        }
+    }
+
+    /// The packages with the given IDs.
+    fn members<'a>(&'a self, ids: &'a [String]) -> impl Iterator<Item = &'a Package> {
+        self.packages
+            .iter()
+            .filter(move |package| ids.contains(&package.id))
+    }
+}
+
+/// The package selection arguments of Cargo commands.
+#[derive(Debug, Clone, Default)]
+pub struct PackageSelection<'a> {
+    /// The package selected with `--package`.
+    pub package: Option<&'a str>,
+    /// Whether all members of the workspace are selected with `--workspace`.
+    pub is_workspace: bool,
+    /// The package excluded from `--workspace` with `--exclude`.
+    pub exclude: Option<&'a str>,
+    /// The directory Cargo starts searching the manifest from.
+    ///
+    /// This is the current directory, unless `--manifest-path` is given.
+    pub manifest_dir: PathBuf,
+}
+
+impl PackageSelection<'_> {
+    /// The directory to search the manifest from, for the given `--manifest-path`.
+    pub fn manifest_dir(manifest_path: Option<&str>) -> io::Result<PathBuf> {
+        let current_dir = env::current_dir()?;
+        Ok(manifest_path
+            .and_then(|manifest_path| {
+                current_dir
+                    .join(manifest_path)
+                    .parent()
+                    .map(Path::to_path_buf)
+            })
+            .unwrap_or(current_dir))
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,6 +165,10 @@ pub struct Package {
     pub name: String,
     /// The version of the package.
     pub version: Version,
+    /// The description of the package, if defined in the manifest.
+    pub description: Option<String>,
+    /// Path to the license file of the package, if defined in the manifest.
+    pub license_file: Option<PathBuf>,
     /// The Package ID for referring to the package within the document and as the `--package`
     /// argument to many commands.
     pub id: String,
@@ -72,6 +178,9 @@ pub struct Package {
     pub manifest_path: PathBuf,
     /// Optional string that is the default binary picked by cargo run.
     pub default_run: Option<String>,
+    /// The contents of the `[package.metadata]` table, or `null` if it is not defined.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
 }
 
 impl Package {
@@ -156,9 +265,137 @@ pub enum TargetKind {
     Unknown(String),
 }
 
+/// Metadata of workspaces for tests.
+#[cfg(test)]
+pub(crate) mod fixture {
+    use serde_json::json;
+
+    use super::Metadata;
+
+    /// A builder for the metadata of a workspace in `/workspace`.
+    #[derive(Default)]
+    pub(crate) struct TestWorkspace {
+        packages: Vec<serde_json::Value>,
+        default_members: Option<Vec<String>>,
+    }
+
+    impl TestWorkspace {
+        pub(crate) fn new() -> Self {
+            Self::default()
+        }
+
+        /// Add a member in the directory relative to the workspace root, with the targets given
+        /// by their kind and name, e.g. `("bin", "game")`.
+        pub(crate) fn package(mut self, name: &str, dir: &str, targets: &[(&str, &str)]) -> Self {
+            let targets: Vec<_> = targets
+                .iter()
+                .map(|(kind, name)| json!({ "kind": [kind], "name": name }))
+                .collect();
+            let manifest_path = std::path::Path::new("/workspace")
+                .join(dir)
+                .join("Cargo.toml");
+
+            self.packages.push(json!({
+                "name": name,
+                "version": "0.1.0",
+                "id": name,
+                "targets": targets,
+                "manifest_path": manifest_path,
+                "default_run": null,
+            }));
+            self
+        }
+
+        /// Select only some members by default, instead of all of them.
+        pub(crate) fn default_members(mut self, names: &[&str]) -> Self {
+            self.default_members = Some(names.iter().map(|name| name.to_string()).collect());
+            self
+        }
+
+        pub(crate) fn build(self) -> Metadata {
+            let ids: Vec<_> = self
+                .packages
+                .iter()
+                .map(|package| package["id"].clone())
+                .collect();
+            let default_members = self.default_members.map_or_else(
+                || ids.clone(),
+                |names| names.into_iter().map(Into::into).collect(),
+            );
+
+            serde_json::from_value(json!({
+                "packages": self.packages,
+                "workspace_members": ids,
+                "workspace_default_members": default_members,
+                "target_directory": "/workspace/target",
+                "workspace_root": "/workspace",
+            }))
+            .unwrap()
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::{fixture::TestWorkspace, *};
+
+    /// A workspace with a root package, two members and a default member.
+    fn workspace_metadata() -> Metadata {
+        TestWorkspace::new()
+            .package("root", "", &[])
+            .package("game", "game", &[])
+            .package("editor", "editor", &[])
+            .default_members(&["game"])
+            .build()
+    }
+
+    fn selected_package_names(metadata: &Metadata, selection: &PackageSelection) -> Vec<String> {
+        metadata
+            .selected_packages(selection)
+            .unwrap()
+            .into_iter()
+            .map(|package| package.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn should_select_packages_like_cargo() {
+        let metadata = workspace_metadata();
+        let in_dir = |dir: &str| PackageSelection {
+            manifest_dir: PathBuf::from(dir),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            selected_package_names(&metadata, &in_dir("/workspace/editor/src")),
+            ["editor"]
+        );
+        // The root manifest selects the default members
+        assert_eq!(
+            selected_package_names(&metadata, &in_dir("/workspace")),
+            ["game"]
+        );
+        assert_eq!(
+            selected_package_names(&metadata, &in_dir("/workspace/assets")),
+            ["game"]
+        );
+
+        let selection = PackageSelection {
+            package: Some("editor"),
+            ..in_dir("/workspace/game")
+        };
+        assert_eq!(selected_package_names(&metadata, &selection), ["editor"]);
+
+        let selection = PackageSelection {
+            is_workspace: true,
+            exclude: Some("root"),
+            ..in_dir("/workspace/game")
+        };
+        assert_eq!(
+            selected_package_names(&metadata, &selection),
+            ["game", "editor"]
+        );
+    }
 
     #[test]
     fn should_determine_metadata_of_this_package() {
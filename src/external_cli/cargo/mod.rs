@@ -1,10 +1,13 @@
-use std::{env, ffi::OsString};
+use std::{env, ffi::OsString, path::PathBuf, process::Command};
 
 use clap::{ArgAction, Args};
 
+use self::{config::ConfiguredRustflags, metadata::Metadata};
 use super::arg_builder::ArgBuilder;
+use crate::config::CliConfig;
 
 pub(crate) mod build;
+pub(crate) mod config;
 pub(crate) mod install;
 pub(crate) mod metadata;
 pub(crate) mod run;
@@ -30,6 +33,15 @@ pub struct CargoFeatureArgs {
 }
 
 impl CargoFeatureArgs {
+    /// Activate the features defined in the configuration, in addition to the given ones.
+    pub(crate) fn apply_config(&mut self, config: &CliConfig) {
+        self.features.extend(config.features.iter().cloned());
+
+        if config.default_features == Some(false) {
+            self.is_no_default_features = true;
+        }
+    }
+
     pub(crate) fn args_builder(&self) -> ArgBuilder {
         ArgBuilder::new()
             .add_value_list("--features", self.features.clone())
@@ -64,9 +76,102 @@ pub struct CargoCompilationArgs {
     /// Directory for all generated artifacts.
     #[clap(long = "target-dir", value_name = "DIRECTORY")]
     pub target_dir: Option<String>,
+
+    /// Space separated list of additional flags to pass to `rustc`.
+    ///
+    /// They are combined with the `rustflags` defined in `.cargo/config.toml`.
+    #[clap(long = "rustflags", value_name = "FLAGS", allow_hyphen_values = true)]
+    pub rustflags: Option<String>,
+
+    /// Flags from the configuration or required by the CLI, placed before `--rustflags`.
+    #[clap(skip)]
+    pub prepended_rustflags: Vec<String>,
 }
 
 impl CargoCompilationArgs {
+    /// Use the profile defined in the configuration, if no profile has been selected explicitly.
+    ///
+    /// The `rustc` flags of the configuration are placed before the ones passed via `--rustflags`.
+    pub(crate) fn apply_config(&mut self, config: &CliConfig) {
+        if self.profile.is_none() && !self.is_release {
+            self.profile.clone_from(&config.profile);
+        }
+
+        self.prepend_rustflags(config.rustflags.iter().cloned());
+    }
+
+    /// The directory Cargo places the build artifacts in.
+    ///
+    /// Without `--target-dir`, this is the directory from the metadata, which already respects
+    /// `CARGO_TARGET_DIR` and the Cargo configuration.
+    pub(crate) fn target_directory(&self, metadata: &Metadata) -> PathBuf {
+        self.target_dir
+            .as_ref()
+            .map_or_else(|| metadata.target_directory.clone(), PathBuf::from)
+    }
+
+    /// Add `rustc` flags before the existing ones, so that the latter can override them.
+    ///
+    /// Each item is passed to `rustc` as a single argument, even if it contains spaces.
+    pub(crate) fn prepend_rustflags<I, S>(&mut self, rustflags: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut rustflags: Vec<String> = rustflags.into_iter().map(Into::into).collect();
+        rustflags.append(&mut self.prepended_rustflags);
+        self.prepended_rustflags = rustflags;
+    }
+
+    /// All additional `rustc` flags, in the order they are passed to `rustc`.
+    fn all_rustflags(&self) -> Vec<String> {
+        let cli_rustflags = self
+            .rustflags
+            .iter()
+            .flat_map(|flags| flags.split_whitespace());
+
+        self.prepended_rustflags
+            .iter()
+            .cloned()
+            .chain(cli_rustflags.map(str::to_owned))
+            .collect()
+    }
+
+    /// Pass the additional `rustc` flags to the `cargo` command.
+    ///
+    /// The flags are defined via `--config`, so that `cargo` combines them with the `rustflags`
+    /// of the `target` tables in `.cargo/config.toml`. These tables take precedence over
+    /// `build.rustflags`, so the configured `build.rustflags` are included in the flags unless a
+    /// `target` table defines its own. Because the configured flags are ignored entirely if
+    /// `RUSTFLAGS` is set, they are appended to the environment variable in that case.
+    ///
+    /// This needs to be called before any arguments for the app are added.
+    pub(crate) fn apply_rustflags(&self, command: &mut Command) {
+        let rustflags = self.all_rustflags();
+        if rustflags.is_empty() {
+            return;
+        }
+
+        match rustflags_from_env() {
+            Some(mut existing) => {
+                existing.extend(rustflags);
+                // In contrast to `RUSTFLAGS`, the encoded variant preserves spaces in the flags
+                command.env("CARGO_ENCODED_RUSTFLAGS", existing.join("\x1f"));
+            }
+            None => {
+                let configured = ConfiguredRustflags::load();
+                let rustflags = if configured.has_target_rustflags {
+                    rustflags
+                } else {
+                    let mut build_rustflags = configured.build;
+                    build_rustflags.extend(rustflags);
+                    build_rustflags
+                };
+                command.arg("--config").arg(rustflags_config(&rustflags));
+            }
+        }
+    }
+
     /// The profile used to compile the app.
     ///
     /// This is determined by the `--release` and `--profile` arguments.
@@ -111,6 +216,35 @@ impl CargoCompilationArgs {
     }
 }
 
+/// The flags defined via the `CARGO_ENCODED_RUSTFLAGS` or `RUSTFLAGS` environment variables.
+///
+/// Returns `None` if neither of them is set, in which case `cargo` uses the configured flags.
+fn rustflags_from_env() -> Option<Vec<String>> {
+    if let Ok(encoded) = env::var("CARGO_ENCODED_RUSTFLAGS") {
+        return Some(
+            encoded
+                .split('\x1f')
+                .filter(|flag| !flag.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        );
+    }
+
+    let rustflags = env::var("RUSTFLAGS").ok()?;
+    Some(rustflags.split_whitespace().map(str::to_owned).collect())
+}
+
+/// The `--config` value adding the `rustc` flags to all targets.
+///
+/// A `target` table is used instead of `build.rustflags`, because the latter is ignored if any
+/// `target` table in `.cargo/config.toml` defines `rustflags`. Arrays in `--config` are also
+/// joined with the arrays of the configuration files, which can't be combined with `rustflags`
+/// defined as a string.
+fn rustflags_config(rustflags: &[String]) -> String {
+    let rustflags: toml_edit::Array = rustflags.iter().collect();
+    format!("target.'cfg(all())'.rustflags={rustflags}")
+}
+
 #[derive(Debug, Args, Clone)]
 #[command(next_help_heading = "Manifest Options")]
 pub struct CargoManifestArgs {
@@ -163,3 +297,45 @@ impl CargoCommonArgs {
         ArgBuilder::new().add_values_separately("--config", self.config.iter())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_place_prepended_rustflags_first() {
+        let mut args = CargoCompilationArgs {
+            is_release: false,
+            profile: None,
+            jobs: None,
+            is_keep_going: false,
+            target: None,
+            target_dir: None,
+            rustflags: Some("-Cdebuginfo=0  --cfg foo".to_owned()),
+            prepended_rustflags: vec!["--cfg".to_owned(), "bar".to_owned()],
+        };
+        args.prepend_rustflags(["--cfg", "getrandom_backend=\"wasm_js\""]);
+
+        assert_eq!(
+            args.all_rustflags(),
+            [
+                "--cfg",
+                "getrandom_backend=\"wasm_js\"",
+                "--cfg",
+                "bar",
+                "-Cdebuginfo=0",
+                "--cfg",
+                "foo"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_preserve_spaces_in_rustflags_config() {
+        let rustflags = ["--cfg".to_owned(), "feature=\"a b\"".to_owned()];
+        assert_eq!(
+            rustflags_config(&rustflags),
+            r#"target.'cfg(all())'.rustflags=["--cfg", 'feature="a b"']"#
+        );
+    }
+}
@@ -1,10 +1,13 @@
-use std::process::Command;
+use std::{io, process::Command};
 
 use clap::Args;
 
 use crate::external_cli::arg_builder::ArgBuilder;
 
-use super::{program, CargoCommonArgs, CargoCompilationArgs, CargoFeatureArgs, CargoManifestArgs};
+use super::{
+    metadata::PackageSelection, program, CargoCommonArgs, CargoCompilationArgs, CargoFeatureArgs,
+    CargoManifestArgs,
+};
 
 /// Create a command to run `cargo run`.
 pub(crate) fn command() -> Command {
@@ -27,6 +30,9 @@ pub struct CargoRunArgs {
     pub compilation_args: CargoCompilationArgs,
     #[clap(flatten)]
     pub manifest_args: CargoManifestArgs,
+    /// Arguments to pass to the app.
+    #[clap(last = true, value_name = "ARGS")]
+    pub app_args: Vec<String>,
 }
 
 impl CargoRunArgs {
@@ -39,6 +45,28 @@ impl CargoRunArgs {
             .append(self.compilation_args.args_builder(is_web))
             .append(self.manifest_args.args_builder())
     }
+
+    /// The packages selected with `--package`.
+    pub(crate) fn package_selection(&self) -> io::Result<PackageSelection<'_>> {
+        Ok(PackageSelection {
+            package: self.package_args.package.as_deref(),
+            manifest_dir: PackageSelection::manifest_dir(
+                self.manifest_args.manifest_path.as_deref(),
+            )?,
+            ..Default::default()
+        })
+    }
+
+    /// Generate the arguments to pass through to the app, separated by `--`.
+    pub(crate) fn app_args_builder(&self) -> ArgBuilder {
+        if self.app_args.is_empty() {
+            return ArgBuilder::new();
+        }
+
+        self.app_args
+            .iter()
+            .fold(ArgBuilder::new().arg("--"), |builder, arg| builder.arg(arg))
+    }
 }
 
 #[derive(Debug, Args, Clone)]
@@ -50,3 +50,156 @@ pub(crate) fn install_target_if_needed(target: &str, silent: bool) -> anyhow::Re
     );
     Ok(())
 }
+
+/// Determine if the given toolchain is installed.
+fn is_toolchain_installed(toolchain: &str) -> bool {
+    let output = Command::new(program())
+        .arg("toolchain")
+        .arg("list")
+        .output();
+
+    let Ok(output) = output else { return false };
+    let list = String::from_utf8_lossy(&output.stdout);
+    contains_toolchain(&list, toolchain, host_triple().as_deref())
+}
+
+/// Determine if the output of `rustup toolchain list` contains the toolchain.
+///
+/// Rustup lists the toolchains with the host triple appended to their name, e.g.
+/// `nightly-x86_64-unknown-linux-gnu (default)` for `nightly`. Only this exact name matches, so
+/// that `nightly` isn't considered installed because of `nightly-2025-01-09-<host>`.
+fn contains_toolchain(list: &str, toolchain: &str, host_triple: Option<&str>) -> bool {
+    let full_name = host_triple.map(|host_triple| format!("{toolchain}-{host_triple}"));
+
+    list.lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .any(|name| name == toolchain || full_name.as_deref() == Some(name))
+}
+
+/// The triple of the host, which rustup installs toolchains for by default.
+fn host_triple() -> Option<String> {
+    let output = Command::new(program()).arg("show").output().ok()?;
+
+    // The output starts with a line like this:
+    // Default host: <target_triple>
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("Default host:"))
+        .map(|host_triple| host_triple.trim().to_owned())
+}
+
+/// Install a toolchain, if it is not already installed.
+pub(crate) fn install_toolchain_if_needed(toolchain: &str, silent: bool) -> anyhow::Result<()> {
+    if is_toolchain_installed(toolchain) {
+        return Ok(());
+    }
+
+    if !silent {
+        // Abort if the user doesn't want to install it
+        if !Confirm::new()
+            .with_prompt(format!(
+                "Toolchain `{toolchain}` is missing, should I install it for you?",
+            ))
+            .interact()?
+        {
+            anyhow::bail!("User does not want to install toolchain `{toolchain}`.");
+        }
+    }
+
+    println!("Installing missing toolchain: `{toolchain}`");
+
+    let mut cmd = Command::new(program());
+    cmd.arg("toolchain")
+        .arg("install")
+        .arg(toolchain)
+        .arg("--profile")
+        .arg("minimal");
+
+    anyhow::ensure!(
+        cmd.output()?.status.success(),
+        "Failed to install toolchain `{toolchain}`."
+    );
+    Ok(())
+}
+
+/// Given a component name, determine if it is already installed for the toolchain.
+///
+/// If no toolchain is given, the active one is used.
+fn is_component_installed(component: &str, toolchain: Option<&str>) -> bool {
+    let mut cmd = Command::new(program());
+    cmd.arg("component").arg("list").arg("--installed");
+    if let Some(toolchain) = toolchain {
+        cmd.arg("--toolchain").arg(toolchain);
+    }
+
+    // Check if the component list has an entry like this:
+    // <component>
+    // Some components are suffixed with the host triple, e.g. `rust-std-<triple>`
+    let Ok(output) = cmd.output() else {
+        return false;
+    };
+    let list = String::from_utf8_lossy(&output.stdout);
+    list.lines()
+        .any(|line| line == component || line.starts_with(&format!("{component}-")))
+}
+
+/// Install a component for the toolchain, if it is not already installed.
+///
+/// If no toolchain is given, the active one is used.
+pub(crate) fn install_component_if_needed(
+    component: &str,
+    toolchain: Option<&str>,
+    silent: bool,
+) -> anyhow::Result<()> {
+    if is_component_installed(component, toolchain) {
+        return Ok(());
+    }
+
+    if !silent {
+        // Abort if the user doesn't want to install it
+        if !Confirm::new()
+            .with_prompt(format!(
+                "Component `{component}` is missing, should I install it for you?",
+            ))
+            .interact()?
+        {
+            anyhow::bail!("User does not want to install component `{component}`.");
+        }
+    }
+
+    println!("Installing missing component: `{component}`");
+
+    let mut cmd = Command::new(program());
+    cmd.arg("component").arg("add").arg(component);
+    if let Some(toolchain) = toolchain {
+        cmd.arg("--toolchain").arg(toolchain);
+    }
+
+    anyhow::ensure!(
+        cmd.output()?.status.success(),
+        "Failed to install component `{component}`."
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_exact_toolchain_name() {
+        let list = "stable-x86_64-unknown-linux-gnu (default)\n\
+                    nightly-2025-01-09-x86_64-unknown-linux-gnu (active)\n";
+        let host_triple = Some("x86_64-unknown-linux-gnu");
+
+        assert!(contains_toolchain(list, "stable", host_triple));
+        assert!(contains_toolchain(list, "nightly-2025-01-09", host_triple));
+        assert!(contains_toolchain(
+            list,
+            "stable-x86_64-unknown-linux-gnu",
+            host_triple
+        ));
+        assert!(!contains_toolchain(list, "nightly", host_triple));
+        assert!(!contains_toolchain(list, "stable", None));
+    }
+}
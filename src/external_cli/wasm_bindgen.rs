@@ -1,4 +1,6 @@
+use clap::ValueEnum;
 use semver::Version;
+use serde::Deserialize;
 use std::{process::Command, str::FromStr};
 
 use crate::{external_cli::CommandHelpers, run::BinTarget};
@@ -8,20 +10,160 @@ use super::arg_builder::ArgBuilder;
 pub(crate) const PACKAGE: &str = "wasm-bindgen-cli";
 pub(crate) const PROGRAM: &str = "wasm-bindgen";
 
+/// The kind of JS bindings created by wasm-bindgen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BindgenTarget {
+    /// An ES module, which can be loaded directly by the browser.
+    #[default]
+    Web,
+    /// A classic script, which defines the `wasm_bindgen` global.
+    NoModules,
+    /// An ES module for bundlers like webpack or Vite, which also load the Wasm binary.
+    Bundler,
+}
+
+impl BindgenTarget {
+    /// The name of the target, as passed to wasm-bindgen.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Web => "web",
+            Self::NoModules => "no-modules",
+            Self::Bundler => "bundler",
+        }
+    }
+}
+
+/// Options for the output of wasm-bindgen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct BindgenOptions {
+    pub target: BindgenTarget,
+    /// Create TypeScript declarations for the bindings.
+    pub typescript: bool,
+    /// Include additional checks and debug information in the bindings.
+    pub debug: bool,
+    /// Keep the debug sections of the Wasm binary.
+    pub keep_debug: bool,
+    /// Free Rust objects with the `FinalizationRegistry` of the browser.
+    pub weak_refs: bool,
+    /// Use the reference types proposal of WebAssembly.
+    pub reference_types: bool,
+}
+
+impl BindgenOptions {
+    /// The names of the files created by wasm-bindgen, excluding the JS modules linked by the app.
+    pub(crate) fn output_file_names(&self, bin_name: &str) -> Vec<String> {
+        let mut file_names = vec![format!("{bin_name}.js"), format!("{bin_name}_bg.wasm")];
+        if self.target == BindgenTarget::Bundler {
+            // The glue code, which is imported by the entrypoint
+            file_names.push(format!("{bin_name}_bg.js"));
+        }
+        if self.typescript {
+            file_names.push(format!("{bin_name}.d.ts"));
+            file_names.push(format!("{bin_name}_bg.wasm.d.ts"));
+        }
+        file_names
+    }
+}
+
+/// How the JavaScript bindings are created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BindgenRunner {
+    /// With the `wasm-bindgen-cli-support` library linked into the CLI.
+    #[cfg(feature = "wasm-bindgen")]
+    InProcess,
+    /// With the installed `wasm-bindgen` program.
+    Cli,
+}
+
+impl BindgenRunner {
+    /// Determine how to create the bindings for an app depending on the given `wasm-bindgen`
+    /// version.
+    ///
+    /// With the `wasm-bindgen` feature, the bindings are created in-process if the linked library
+    /// understands the schema of the app's `wasm-bindgen` version. Otherwise, the `wasm-bindgen`
+    /// program of exactly the same version needs to be installed.
+    pub(crate) fn for_version(version: &Version) -> Self {
+        #[cfg(feature = "wasm-bindgen")]
+        if is_same_schema(
+            wasm_bindgen_shared::SCHEMA_VERSION,
+            &wasm_bindgen_shared::version(),
+            version,
+        ) {
+            return Self::InProcess;
+        }
+
+        #[cfg(not(feature = "wasm-bindgen"))]
+        let _ = version;
+
+        Self::Cli
+    }
+}
+
+/// Determine if `version` uses the schema of the linked `wasm-bindgen` library.
+///
+/// The schema version is the version which last changed the schema, so all versions from it up to
+/// the linked version share the schema. Newer versions might have changed it.
+#[cfg_attr(not(feature = "wasm-bindgen"), allow(dead_code))]
+fn is_same_schema(schema_version: &str, linked_version: &str, version: &Version) -> bool {
+    // The version might be followed by a commit hash, e.g. `0.2.99 (abc123)`
+    let parse = |version: &str| {
+        version
+            .split_whitespace()
+            .next()
+            .and_then(|version| Version::from_str(version).ok())
+    };
+
+    match (parse(schema_version), parse(linked_version)) {
+        (Some(schema_version), Some(linked_version)) => {
+            schema_version <= *version && *version <= linked_version
+        }
+        _ => false,
+    }
+}
+
 /// Bundle the Wasm build for the web.
-pub(crate) fn bundle(bin_target: &BinTarget) -> anyhow::Result<()> {
+///
+/// The bindings are named after `out_name`, e.g. `<out_name>.js` and `<out_name>_bg.wasm`.
+/// For multithreaded builds, the JS modules linked by the app are split into separate files, so
+/// that they can be loaded by the web workers.
+pub(crate) fn bundle(
+    bin_target: &BinTarget,
+    out_name: &str,
+    is_multithreaded: bool,
+    options: &BindgenOptions,
+    runner: BindgenRunner,
+) -> anyhow::Result<()> {
     let original_wasm = bin_target
         .artifact_directory
         .clone()
         .join(format!("{}.wasm", bin_target.bin_name));
 
+    #[cfg(feature = "wasm-bindgen")]
+    if runner == BindgenRunner::InProcess {
+        return bundle_in_process(
+            &original_wasm,
+            &bin_target.artifact_directory,
+            out_name,
+            is_multithreaded,
+            options,
+        );
+    }
+    #[cfg(not(feature = "wasm-bindgen"))]
+    let _ = runner;
+
     Command::new(PROGRAM)
         .args(
             ArgBuilder::new()
-                .arg("--no-typescript")
-                .add_with_value("--out-name", &bin_target.bin_name)
+                .add_flag_if("--no-typescript", !options.typescript)
+                .add_with_value("--out-name", out_name)
                 .add_with_value("--out-dir", bin_target.artifact_directory.to_string_lossy())
-                .add_with_value("--target", "web")
+                .add_with_value("--target", options.target.as_str())
+                .add_flag_if("--debug", options.debug)
+                .add_flag_if("--keep-debug", options.keep_debug)
+                .add_flag_if("--weak-refs", options.weak_refs)
+                .add_flag_if("--reference-types", options.reference_types)
+                .add_flag_if("--split-linked-modules", is_multithreaded)
                 .arg(original_wasm.to_string_lossy()),
         )
         .ensure_status()?;
@@ -29,6 +171,39 @@ pub(crate) fn bundle(bin_target: &BinTarget) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Create the bindings with the linked `wasm-bindgen-cli-support` library, like [`bundle`].
+///
+/// Weak references are always used by the library, so the option has no effect.
+#[cfg(feature = "wasm-bindgen")]
+fn bundle_in_process(
+    wasm_path: &std::path::Path,
+    out_dir: &std::path::Path,
+    out_name: &str,
+    is_multithreaded: bool,
+    options: &BindgenOptions,
+) -> anyhow::Result<()> {
+    let mut bindgen = wasm_bindgen_cli_support::Bindgen::new();
+    bindgen
+        .input_path(wasm_path)
+        .out_name(out_name)
+        .typescript(options.typescript)
+        .debug(options.debug)
+        .keep_debug(options.keep_debug)
+        .split_linked_modules(is_multithreaded);
+    if options.reference_types {
+        // Like `--reference-types`, for binaries compiled without the target feature
+        #[allow(deprecated)]
+        bindgen.reference_types(true);
+    }
+    match options.target {
+        BindgenTarget::Web => bindgen.web(true)?,
+        BindgenTarget::NoModules => bindgen.no_modules(true)?,
+        BindgenTarget::Bundler => bindgen.bundler(true)?,
+    };
+
+    bindgen.generate(out_dir)
+}
+
 /// Transforms the output from `wasm-bindgen --version` into a [Version].
 pub(crate) fn wasm_bindgen_cli_version(stdout: &[u8]) -> anyhow::Result<Version> {
     let stdout = String::from_utf8_lossy(stdout);
@@ -44,3 +219,46 @@ pub(crate) fn wasm_bindgen_cli_version(stdout: &[u8]) -> anyhow::Result<Version>
         })
         .and_then(|version| Version::from_str(version).map_err(|e| anyhow::anyhow!(e)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_match_versions_of_same_schema() {
+        let version = |version| Version::from_str(version).unwrap();
+
+        assert!(is_same_schema("0.2.98", "0.2.99", &version("0.2.98")));
+        assert!(is_same_schema(
+            "0.2.98",
+            "0.2.99 (abc123)",
+            &version("0.2.99")
+        ));
+        assert!(!is_same_schema("0.2.98", "0.2.99", &version("0.2.97")));
+        assert!(!is_same_schema("0.2.98", "0.2.99", &version("0.2.100")));
+    }
+
+    #[test]
+    fn should_list_output_files_of_target() {
+        assert_eq!(
+            BindgenOptions::default().output_file_names("app"),
+            ["app.js", "app_bg.wasm"]
+        );
+
+        let options = BindgenOptions {
+            target: BindgenTarget::Bundler,
+            typescript: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.output_file_names("app"),
+            [
+                "app.js",
+                "app_bg.wasm",
+                "app_bg.js",
+                "app.d.ts",
+                "app_bg.wasm.d.ts"
+            ]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Copying files into bundle folders and walking them, e.g. to pack them into archives.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+
+/// Copy the Bevy assets folder into the `assets` folder of the bundle, if it exists.
+pub(crate) fn copy_assets(assets_path: &Path, bundle_path: &Path) -> anyhow::Result<()> {
+    if assets_path.exists() {
+        copy_dir_contents(assets_path, &bundle_path.join("assets"))
+            .context("failed to copy assets")?;
+    }
+
+    Ok(())
+}
+
+/// Copy the contents of the `source` folder into `destination`, replacing existing files.
+pub(crate) fn copy_dir_contents(source: &Path, destination: &Path) -> anyhow::Result<()> {
+    fs_extra::dir::copy(
+        source,
+        destination,
+        &fs_extra::dir::CopyOptions {
+            overwrite: true,
+            content_only: true,
+            ..Default::default()
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Recursively find all files in the folder, mapped by their [`relative_name`].
+///
+/// The map is sorted by the names, so it doesn't depend on the order of the directory entries.
+pub(crate) fn files_by_name(base_path: &Path) -> anyhow::Result<BTreeMap<String, PathBuf>> {
+    let mut files = BTreeMap::new();
+    collect_files(base_path, base_path, &mut files)?;
+    Ok(files)
+}
+
+fn collect_files(
+    base_path: &Path,
+    path: &Path,
+    files: &mut BTreeMap<String, PathBuf>,
+) -> anyhow::Result<()> {
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            collect_files(base_path, &path, files)?;
+        } else {
+            files.insert(relative_name(base_path, &path), path);
+        }
+    }
+
+    Ok(())
+}
+
+/// The path relative to `base_path`, always using `/` as separator like URLs and archives.
+pub(crate) fn relative_name(base_path: &Path, path: &Path) -> String {
+    path.strip_prefix(base_path)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_find_files_by_relative_name() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_path = temp_dir.path();
+        fs::create_dir_all(base_path.join("assets/textures")).unwrap();
+        fs::write(base_path.join("index.html"), "").unwrap();
+        fs::write(base_path.join("assets/textures/icon.png"), "").unwrap();
+
+        let files = files_by_name(base_path).unwrap();
+        assert_eq!(
+            files.into_iter().collect::<Vec<_>>(),
+            [
+                (
+                    "assets/textures/icon.png".to_owned(),
+                    base_path.join("assets/textures/icon.png")
+                ),
+                ("index.html".to_owned(), base_path.join("index.html")),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_use_url_separators() {
+        assert_eq!(
+            relative_name(Path::new("/bundle"), Path::new("/bundle/assets/icon.png")),
+            "assets/icon.png"
+        );
+    }
+}
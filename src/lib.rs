@@ -1,8 +1,11 @@
 //! The library backend for the Bevy CLI.
 
 pub mod build;
+pub(crate) mod config;
 pub mod external_cli;
+pub(crate) mod files;
 pub mod lint;
+pub(crate) mod native;
 pub mod run;
 pub mod template;
 pub(crate) mod web;
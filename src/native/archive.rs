@@ -0,0 +1,127 @@
+//! Packing native bundles into `.tar.gz` archives, which keep the binary executable.
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use flate2::{write::GzEncoder, Compression};
+use tar::{Builder, Header};
+
+use crate::files;
+
+/// Pack the bundle folder into a `.tar.gz` archive next to it.
+///
+/// The files are placed in a folder named like the bundle, so the archive extracts into a single
+/// folder. Only the `executable` at the root of the bundle is marked as executable.
+///
+/// All entries have the same timestamp and owner, so the archive only changes with the contents
+/// of the bundle.
+///
+/// Returns the path to the created archive.
+pub(crate) fn create_tar_gz(bundle_path: &Path, executable: &str) -> anyhow::Result<PathBuf> {
+    let mut archive_path = bundle_path.as_os_str().to_owned();
+    archive_path.push(".tar.gz");
+    let archive_path = PathBuf::from(archive_path);
+
+    let folder_name = bundle_path
+        .file_name()
+        .context("the bundle needs a folder name")?
+        .to_string_lossy()
+        .into_owned();
+
+    let files = files::files_by_name(bundle_path)?;
+
+    let archive_file = File::create(&archive_path)
+        .with_context(|| format!("failed to create {}", archive_path.display()))?;
+    let mut archive = Builder::new(GzEncoder::new(
+        BufWriter::new(archive_file),
+        Compression::default(),
+    ));
+
+    for (name, path) in files {
+        let file =
+            File::open(&path).with_context(|| format!("failed to read {}", path.display()))?;
+
+        let mut header = Header::new_gnu();
+        header.set_size(file.metadata()?.len());
+        header.set_mode(if name == executable { 0o755 } else { 0o644 });
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        // The file is streamed into the archive, so large assets aren't loaded into memory
+        archive.append_data(&mut header, format!("{folder_name}/{name}"), file)?;
+    }
+
+    archive.into_inner()?.finish()?;
+
+    Ok(archive_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use flate2::read::GzDecoder;
+    use tar::Archive;
+
+    use super::*;
+
+    /// Create a bundle folder with a binary and some assets.
+    fn create_bundle(path: &Path) {
+        fs::create_dir_all(path.join("assets/textures")).unwrap();
+        fs::write(path.join("my_game"), b"binary").unwrap();
+        fs::write(path.join("my_game.desktop"), "[Desktop Entry]\n").unwrap();
+        fs::write(path.join("assets/textures/player.png"), [1, 2, 3]).unwrap();
+    }
+
+    #[test]
+    fn should_create_reproducible_archive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bundle_path = temp_dir.path().join("my_game");
+        create_bundle(&bundle_path);
+
+        let archive_path = create_tar_gz(&bundle_path, "my_game").unwrap();
+        assert_eq!(archive_path, temp_dir.path().join("my_game.tar.gz"));
+        let first = fs::read(&archive_path).unwrap();
+
+        // Recreating the folder changes the file timestamps and possibly the directory order
+        fs::remove_dir_all(&bundle_path).unwrap();
+        create_bundle(&bundle_path);
+
+        let second = fs::read(create_tar_gz(&bundle_path, "my_game").unwrap()).unwrap();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn should_keep_binary_executable() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bundle_path = temp_dir.path().join("my_game");
+        create_bundle(&bundle_path);
+
+        let archive_path = create_tar_gz(&bundle_path, "my_game").unwrap();
+        let mut archive = Archive::new(GzDecoder::new(File::open(archive_path).unwrap()));
+        let entries: Vec<_> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (
+                    entry.path().unwrap().to_string_lossy().into_owned(),
+                    entry.header().mode().unwrap(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            entries,
+            [
+                ("my_game/assets/textures/player.png".to_owned(), 0o644),
+                ("my_game/my_game".to_owned(), 0o755),
+                ("my_game/my_game.desktop".to_owned(), 0o644),
+            ]
+        );
+    }
+}
@@ -0,0 +1,312 @@
+//! Bundling native apps into a single folder, so that Linux builds are packaged the same way
+//! every time.
+//!
+//! Next to the binary, the bundle contains the assets, the licenses of the package and a desktop
+//! entry with an icon, which adds the app to the application menus of Linux desktops.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{ensure, Context as _};
+use image::imageops::FilterType;
+
+use crate::{
+    external_cli::cargo::metadata::{Metadata, Package},
+    files,
+    run::BinTarget,
+};
+
+/// The width and height of the bundled icon in pixels.
+const ICON_SIZE: u32 = 256;
+
+/// The beginnings of the file names which are considered licenses, in uppercase.
+const LICENSE_PREFIXES: [&str; 3] = ["LICENSE", "LICENCE", "COPYING"];
+
+/// The directory containing the native bundles of the given profile,
+/// `target/bevy_native/{profile}`.
+pub(crate) fn bundles_directory(metadata: &Metadata, profile: &str) -> PathBuf {
+    metadata.target_directory.join("bevy_native").join(profile)
+}
+
+/// Determine if apps compiled for the target can be bundled, which is only supported on Linux.
+///
+/// Without a target, the app is compiled for the host.
+pub(crate) fn is_supported_target(target: Option<&str>) -> bool {
+    target.map_or(cfg!(target_os = "linux"), |target| {
+        target.contains("-linux-")
+    })
+}
+
+/// Pack the compiled binary, the assets, the license files and a desktop entry into the folder
+/// `{bin}` of the `bundles_directory`, see [`bundles_directory`].
+///
+/// The icon is scaled to 256x256 pixels and named after the binary, like the desktop entry.
+///
+/// Returns the path to the created bundle.
+pub(crate) fn create_native_bundle(
+    bundles_directory: &Path,
+    bin_target: &BinTarget,
+    description: Option<&str>,
+    assets_path: &Path,
+    license_files: &[PathBuf],
+    icon_path: Option<&Path>,
+) -> anyhow::Result<PathBuf> {
+    let bin_name = &bin_target.bin_name;
+    let binary_path = bin_target.artifact_directory.join(bin_name);
+    ensure!(
+        binary_path.is_file(),
+        "failed to find the compiled binary at {}",
+        binary_path.display()
+    );
+
+    let base_path = bundles_directory.join(bin_name);
+
+    // Remove the previous bundle
+    // The error can be ignored, because the folder doesn't need to exist yet
+    let _ = fs::remove_dir_all(&base_path);
+    fs::create_dir_all(&base_path)?;
+
+    // The permissions are copied as well, so the binary stays executable
+    fs::copy(&binary_path, base_path.join(bin_name)).context("failed to copy the binary")?;
+
+    // Bevy looks for the assets next to the binary
+    files::copy_assets(assets_path, &base_path)?;
+
+    for license_file in license_files {
+        let file_name = license_file
+            .file_name()
+            .with_context(|| format!("invalid license file {}", license_file.display()))?;
+        fs::copy(license_file, base_path.join(file_name))
+            .with_context(|| format!("failed to copy license file {}", license_file.display()))?;
+    }
+
+    if let Some(icon_path) = icon_path {
+        let icon = image::open(icon_path)
+            .with_context(|| format!("failed to read icon {}", icon_path.display()))?;
+        icon.resize_to_fill(ICON_SIZE, ICON_SIZE, FilterType::Lanczos3)
+            .save(base_path.join(format!("{bin_name}.png")))
+            .context("failed to create icon")?;
+    } else {
+        println!("No icon configured, the desktop entry will use a generic one.");
+    }
+
+    fs::write(
+        base_path.join(format!("{bin_name}.desktop")),
+        desktop_entry(bin_target, description, icon_path.is_some()),
+    )
+    .context("failed to create desktop entry")?;
+
+    Ok(base_path)
+}
+
+/// The license files to bundle with the package.
+///
+/// These are the files starting with `LICENSE`, `LICENCE` or `COPYING` in the package directory,
+/// or in the workspace root if the package has none, and the `license-file` of the manifest.
+pub(crate) fn license_files(
+    package: &Package,
+    workspace_root: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let package_dir = package.manifest_path.parent().unwrap_or(Path::new(""));
+
+    let mut license_files = find_license_files(package_dir)
+        .with_context(|| format!("failed to read {}", package_dir.display()))?;
+    if license_files.is_empty() {
+        license_files = find_license_files(workspace_root)
+            .with_context(|| format!("failed to read {}", workspace_root.display()))?;
+    }
+
+    if let Some(license_file) = &package.license_file {
+        let license_file = package_dir.join(license_file);
+        if !license_files.contains(&license_file) {
+            license_files.push(license_file);
+        }
+    }
+
+    license_files.sort();
+    Ok(license_files)
+}
+
+/// Find the files in the directory whose names start with one of the [`LICENSE_PREFIXES`].
+fn find_license_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut license_files = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_uppercase();
+
+        if path.is_file()
+            && LICENSE_PREFIXES
+                .iter()
+                .any(|prefix| file_name.starts_with(prefix))
+        {
+            license_files.push(path);
+        }
+    }
+
+    Ok(license_files)
+}
+
+/// The desktop entry of the app, see the
+/// [specification](https://specifications.freedesktop.org/desktop-entry-spec/latest/).
+///
+/// The entry refers to the binary and the icon by name, so they are found once the app is
+/// installed, e.g. by linking the binary into a folder of the `PATH`.
+fn desktop_entry(bin_target: &BinTarget, description: Option<&str>, has_icon: bool) -> String {
+    let mut lines = vec![
+        "[Desktop Entry]".to_owned(),
+        "Type=Application".to_owned(),
+        format!("Name={}", escape_value(&bin_target.package_name)),
+    ];
+    if let Some(description) = description {
+        lines.push(format!("Comment={}", escape_value(description)));
+    }
+    lines.push(format!("Exec={}", bin_target.bin_name));
+    if has_icon {
+        lines.push(format!("Icon={}", bin_target.bin_name));
+    }
+    lines.push("Terminal=false".to_owned());
+    lines.push("Categories=Game;".to_owned());
+
+    lines.join("\n") + "\n"
+}
+
+/// Escape the characters which can't appear literally in values of desktop entries.
+fn escape_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageBuffer, Rgba};
+    use serde_json::json;
+
+    use super::*;
+
+    fn bin_target(artifact_directory: &Path) -> BinTarget {
+        BinTarget {
+            artifact_directory: artifact_directory.to_path_buf(),
+            bin_name: "my_game".to_owned(),
+            package_name: "my-game".to_owned(),
+            package_version: "0.1.0".to_owned(),
+        }
+    }
+
+    #[test]
+    fn should_create_desktop_entry() {
+        let bin_target = bin_target(Path::new("target/release"));
+
+        assert_eq!(
+            desktop_entry(&bin_target, Some("A game\nwith two lines"), true),
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=my-game\n\
+             Comment=A game\\nwith two lines\n\
+             Exec=my_game\n\
+             Icon=my_game\n\
+             Terminal=false\n\
+             Categories=Game;\n"
+        );
+        assert!(!desktop_entry(&bin_target, None, false).contains("Icon="));
+    }
+
+    #[test]
+    fn should_find_license_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let workspace_root = temp_dir.path();
+        let package_dir = workspace_root.join("crates/game");
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::write(workspace_root.join("LICENSE-MIT"), "MIT").unwrap();
+        fs::write(workspace_root.join("Copying.txt"), "GPL").unwrap();
+        fs::write(workspace_root.join("README.md"), "Game").unwrap();
+
+        let package: Package = serde_json::from_value(json!({
+            "name": "game",
+            "version": "0.1.0",
+            "id": "game",
+            "targets": [],
+            "manifest_path": package_dir.join("Cargo.toml"),
+            "license_file": "CREDITS.md",
+        }))
+        .unwrap();
+
+        // Licenses of the workspace apply if the package has none
+        assert_eq!(
+            license_files(&package, workspace_root).unwrap(),
+            [
+                workspace_root.join("Copying.txt"),
+                workspace_root.join("LICENSE-MIT"),
+                package_dir.join("CREDITS.md"),
+            ]
+        );
+
+        fs::write(package_dir.join("LICENSE"), "Proprietary").unwrap();
+        assert_eq!(
+            license_files(&package, workspace_root).unwrap(),
+            [package_dir.join("CREDITS.md"), package_dir.join("LICENSE")]
+        );
+    }
+
+    #[test]
+    fn should_create_native_bundle() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_path = temp_dir.path();
+        let artifact_directory = base_path.join("target/release");
+        fs::create_dir_all(&artifact_directory).unwrap();
+        fs::write(artifact_directory.join("my_game"), b"binary").unwrap();
+        fs::create_dir_all(base_path.join("game_assets/textures")).unwrap();
+        fs::write(base_path.join("game_assets/textures/player.png"), b"png").unwrap();
+        fs::write(base_path.join("LICENSE"), "MIT").unwrap();
+
+        let icon_path = base_path.join("icon.png");
+        ImageBuffer::from_pixel(64, 32, Rgba([255u8, 0, 0, 255]))
+            .save(&icon_path)
+            .unwrap();
+
+        let bundle_path = create_native_bundle(
+            &base_path.join("target/bevy_native/release"),
+            &bin_target(&artifact_directory),
+            None,
+            &base_path.join("game_assets"),
+            &[base_path.join("LICENSE")],
+            Some(&icon_path),
+        )
+        .unwrap();
+
+        assert_eq!(
+            bundle_path,
+            base_path.join("target/bevy_native/release/my_game")
+        );
+        assert_eq!(fs::read(bundle_path.join("my_game")).unwrap(), b"binary");
+        assert!(bundle_path.join("assets/textures/player.png").is_file());
+        assert!(bundle_path.join("LICENSE").is_file());
+        assert!(bundle_path.join("my_game.desktop").is_file());
+
+        let icon = image::open(bundle_path.join("my_game.png")).unwrap();
+        assert_eq!((icon.width(), icon.height()), (ICON_SIZE, ICON_SIZE));
+    }
+
+    #[test]
+    fn should_require_compiled_binary() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        assert!(create_native_bundle(
+            &temp_dir.path().join("bundles"),
+            &bin_target(temp_dir.path()),
+            None,
+            &temp_dir.path().join("assets"),
+            &[],
+            None,
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,4 @@
+//! Utilities for distributing the app natively.
+
+pub(crate) mod archive;
+pub(crate) mod bundle;
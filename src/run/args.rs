@@ -1,8 +1,12 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
 use clap::{ArgAction, Args, Subcommand};
 
 use crate::{
     build::args::{BuildArgs, BuildSubcommands, BuildWebArgs},
+    config::CliConfig,
     external_cli::{arg_builder::ArgBuilder, cargo::run::CargoRunArgs},
+    web::{backend::Backend, wasm_opt::OptimizationLevel},
 };
 
 use super::cargo::build::{CargoBuildArgs, CargoPackageBuildArgs, CargoTargetBuildArgs};
@@ -17,6 +21,12 @@ pub struct RunArgs {
     #[arg(long = "yes", default_value_t = false)]
     pub skip_prompts: bool,
 
+    /// Rebuild and restart the app when files change.
+    ///
+    /// In the browser, the page is reloaded automatically.
+    #[arg(short = 'w', long = "watch", global = true, action = ArgAction::SetTrue, default_value_t = false)]
+    pub watch: bool,
+
     /// Commands to forward to `cargo run`.
     #[clap(flatten)]
     pub cargo_args: CargoRunArgs,
@@ -32,6 +42,35 @@ impl RunArgs {
     pub(crate) fn cargo_args_builder(&self) -> ArgBuilder {
         self.cargo_args.args_builder(self.is_web())
     }
+
+    /// Apply the configuration from the manifest.
+    ///
+    /// Arguments passed on the command line take precedence over the configuration.
+    pub(crate) fn apply_config(&mut self, config: &CliConfig) {
+        self.cargo_args.feature_args.apply_config(config);
+        self.cargo_args.compilation_args.apply_config(config);
+
+        if let Some(RunSubcommands::Web(web_args)) = &mut self.subcommand {
+            web_args.host = web_args.host.take().or_else(|| config.host.clone());
+            web_args.port = web_args.port.or(config.port);
+            web_args.https = web_args.https.or(config.https);
+            web_args.open = web_args.open.or(config.open);
+            web_args.create_packed_bundle = web_args.create_packed_bundle.or(config.bundle);
+            web_args.precompress = web_args.precompress.or(config.precompress);
+            web_args.hash_file_names = web_args.hash_file_names.or(config.hash);
+            web_args.hash_assets = web_args.hash_assets.or(config.hash_assets);
+            web_args.backend = web_args.backend.or(config.backend);
+            web_args.wasm_opt_level = web_args.wasm_opt_level.or(config.wasm_opt_level);
+            // The arguments of the configuration come first, so that they can be extended
+            web_args
+                .wasm_opt_args
+                .splice(0..0, config.wasm_opt_args.iter().cloned());
+            web_args.is_multithreaded = web_args.is_multithreaded.or(config.threads);
+            web_args.template_values.clone_from(&config.template_values);
+            web_args.assets_path.clone_from(&config.assets_path);
+            web_args.web_path.clone_from(&config.web_path);
+        }
+    }
 }
 
 #[derive(Debug, Subcommand, Clone)]
@@ -40,25 +79,183 @@ pub enum RunSubcommands {
     Web(RunWebArgs),
 }
 
+/// The host address the web server runs on, if not specified otherwise.
+const DEFAULT_HOST: &str = "127.0.0.1";
+
+/// The port the web server runs on, if not specified otherwise.
+const DEFAULT_PORT: u16 = 4000;
+
 #[derive(Debug, Args, Clone)]
 pub struct RunWebArgs {
-    /// The port to run the web server on.
-    #[arg(short, long, default_value_t = 4000)]
-    pub port: u16,
+    /// The host address to run the web server on [default: 127.0.0.1]
+    ///
+    /// Use `0.0.0.0` to make the app reachable from other devices in your network.
+    #[arg(long, value_name = "HOST")]
+    pub host: Option<String>,
+
+    /// The port to run the web server on [default: 4000]
+    #[arg(short, long)]
+    pub port: Option<u16>,
+
+    /// Serve the app via HTTPS, e.g. to use browser APIs that require a secure context.
+    ///
+    /// Without `--cert` and `--key`, a self-signed certificate is generated.
+    #[arg(
+        long = "https",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub https: Option<bool>,
+
+    /// The certificate to serve the app via HTTPS with, in PEM format.
+    #[arg(long, value_name = "PATH", requires = "key")]
+    pub cert: Option<PathBuf>,
+
+    /// The private key of the certificate, in PEM format.
+    #[arg(long, value_name = "PATH", requires = "cert")]
+    pub key: Option<PathBuf>,
+
+    /// Show a QR code of the app URL, to open it on mobile devices.
+    #[arg(long = "qr", action = ArgAction::SetTrue, default_value_t = false)]
+    pub show_qr_code: bool,
 
     /// Open the app in the browser.
-    #[arg(short = 'o', long = "open", action = ArgAction::SetTrue, default_value_t = false)]
-    pub open: bool,
+    #[arg(
+        short = 'o',
+        long = "open",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub open: Option<bool>,
 
     // Bundle all web artifacts into a single folder.
-    #[arg(short = 'b', long = "bundle", action = ArgAction::SetTrue, default_value_t = false)]
-    pub create_packed_bundle: bool,
+    #[arg(
+        short = 'b',
+        long = "bundle",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub create_packed_bundle: Option<bool>,
+
+    /// Build all examples of the package, with a page linking to each of them.
+    ///
+    /// Implies `--bundle`. Each example is served at `{example}.html` of the bundle.
+    #[arg(long = "examples", action = ArgAction::SetTrue, default_value_t = false)]
+    pub examples: bool,
+
+    /// Create gzip and Brotli compressed copies of the bundled files, implies `--bundle`.
+    #[arg(
+        long = "precompress",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub precompress: Option<bool>,
+
+    /// Add content hashes to the file names of the build artifacts, implies `--bundle`.
+    ///
+    /// A manifest of the renamed files is written to `asset-manifest.json`.
+    #[arg(
+        long = "hash",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub hash_file_names: Option<bool>,
+
+    /// Add copies of the assets with content hashes in their file names, implies `--hash`.
+    #[arg(
+        long = "hash-assets",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub hash_assets: Option<bool>,
+
+    /// The rendering backend to build the app for.
+    ///
+    /// Activates the `webgpu` or `webgl2` feature of the package, which should enable the
+    /// corresponding Bevy feature. With `both`, the app is built twice and `index.html` loads the
+    /// WebGPU build if the browser supports it.
+    #[arg(long = "backend", value_name = "BACKEND")]
+    pub backend: Option<Backend>,
+
+    /// The optimization level of wasm-opt in release builds [default: s]
+    #[arg(long = "wasm-opt-level", value_name = "LEVEL")]
+    pub wasm_opt_level: Option<OptimizationLevel>,
+
+    /// Additional arguments for wasm-opt in release builds, e.g. `--wasm-opt-arg=--enable-simd`.
+    #[arg(long = "wasm-opt-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub wasm_opt_args: Vec<String>,
+
+    /// Enable multithreading, using Wasm threads.
+    ///
+    /// This requires a nightly toolchain and serves the app with cross-origin isolation headers.
+    #[arg(
+        long = "threads",
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub is_multithreaded: Option<bool>,
+
+    /// Custom values for the placeholders in `index.html`, from the configuration.
+    #[clap(skip)]
+    pub template_values: BTreeMap<String, String>,
+
+    /// The assets folder, relative to `BEVY_ASSET_ROOT` or the package directory, from the
+    /// configuration.
+    #[clap(skip)]
+    pub assets_path: Option<PathBuf>,
+
+    /// The folder with the custom `index.html`, relative to the package directory, from the
+    /// configuration.
+    #[clap(skip)]
+    pub web_path: Option<PathBuf>,
+
+    /// Arguments to pass to the app, via the query string of the page URL.
+    #[arg(last = true, value_name = "ARGS")]
+    pub app_args: Vec<String>,
+}
+
+impl RunWebArgs {
+    /// The host address to run the web server on.
+    pub(crate) fn host(&self) -> &str {
+        self.host.as_deref().unwrap_or(DEFAULT_HOST)
+    }
+
+    /// The port to run the web server on.
+    pub(crate) fn port(&self) -> u16 {
+        self.port.unwrap_or(DEFAULT_PORT)
+    }
+
+    /// Whether to serve the app via HTTPS.
+    ///
+    /// Providing a certificate implies `--https`.
+    pub(crate) fn is_https(&self) -> bool {
+        self.https.unwrap_or_default() || self.cert.is_some()
+    }
 }
 
 impl From<RunArgs> for BuildArgs {
     fn from(args: RunArgs) -> Self {
         BuildArgs {
             skip_prompts: args.skip_prompts,
+            // Native bundles are only created by `bevy build`
+            create_native_bundle: None,
+            create_tar: None,
+            icon: None,
+            assets_path: None,
             cargo_args: CargoBuildArgs {
                 common_args: args.cargo_args.common_args,
                 compilation_args: args.cargo_args.compilation_args,
@@ -85,8 +282,90 @@ impl From<RunArgs> for BuildArgs {
             subcommand: args.subcommand.map(|subcommand| match subcommand {
                 RunSubcommands::Web(web_args) => BuildSubcommands::Web(BuildWebArgs {
                     create_packed_bundle: web_args.create_packed_bundle,
+                    examples: web_args.examples,
+                    precompress: web_args.precompress,
+                    hash_file_names: web_args.hash_file_names,
+                    hash_assets: web_args.hash_assets,
+                    // Archives and PWAs are only useful for deployments, so these options
+                    // only apply to `bevy build`
+                    create_zip: None,
+                    pwa: None,
+                    pwa_icon: None,
+                    pwa_theme_color: None,
+                    size_report: false,
+                    size_report_json: None,
+                    backend: web_args.backend,
+                    // The app is served with the bindings it is developed with
+                    bindgen_target: None,
+                    typescript: None,
+                    bindgen_debug: None,
+                    keep_debug: None,
+                    weak_refs: None,
+                    reference_types: None,
+                    wasm_opt_level: web_args.wasm_opt_level,
+                    wasm_opt_args: web_args.wasm_opt_args,
+                    is_multithreaded: web_args.is_multithreaded,
+                    template_values: web_args.template_values,
+                    assets_path: web_args.assets_path,
+                    web_path: web_args.web_path,
                 }),
             }),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: RunArgs,
+    }
+
+    fn parse_web_args(args: &[&str], config: &CliConfig) -> RunWebArgs {
+        let mut args = Cli::try_parse_from(["bevy"].iter().chain(args))
+            .unwrap()
+            .args;
+        args.apply_config(config);
+        match args.subcommand {
+            Some(RunSubcommands::Web(web_args)) => web_args,
+            None => panic!("expected the web subcommand"),
+        }
+    }
+
+    #[test]
+    fn should_override_config_with_flags() {
+        let config = CliConfig {
+            open: Some(true),
+            precompress: Some(false),
+            ..Default::default()
+        };
+
+        let web_args = parse_web_args(&["web"], &config);
+        assert_eq!(web_args.open, Some(true));
+        assert_eq!(web_args.precompress, Some(false));
+
+        let web_args = parse_web_args(&["web", "--open=false", "--precompress"], &config);
+        assert_eq!(web_args.open, Some(false));
+        assert_eq!(web_args.precompress, Some(true));
+    }
+
+    #[test]
+    fn should_build_for_configured_backend() {
+        let config = CliConfig {
+            backend: Some(Backend::Both),
+            ..Default::default()
+        };
+        let mut args = Cli::try_parse_from(["bevy", "web"]).unwrap().args;
+        args.apply_config(&config);
+
+        let Some(BuildSubcommands::Web(web_args)) = BuildArgs::from(args).subcommand else {
+            panic!("expected the web subcommand");
+        };
+        assert_eq!(web_args.backend, Some(Backend::Both));
+    }
+}
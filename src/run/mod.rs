@@ -1,31 +1,109 @@
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    process::{Child, Command},
+    sync::{Arc, PoisonError, RwLock},
+    thread,
+};
+
+use anyhow::Context as _;
+use qrcode::{render::unicode::Dense1x2, QrCode};
 
 use args::RunSubcommands;
+use native::NativeBinary;
+use serve::LiveReload;
+use watch::FileWatcher;
 
 use crate::{
-    build::build_web,
+    build::{args::BuildArgs, build_web},
+    config::CliConfig,
     external_cli::{
-        cargo::{self, metadata::Metadata},
+        cargo::{
+            self,
+            metadata::{Metadata, Package, PackageSelection, Target},
+        },
         CommandHelpers,
     },
+    web::{bundle::WebBundle, folders::PackageFolders},
 };
 
 pub use self::args::RunArgs;
+pub(crate) use self::picker::pick_run_target;
 
 mod args;
+mod native;
+mod picker;
 mod serve;
+mod tls;
+mod watch;
+
+pub fn run(args: &mut RunArgs) -> anyhow::Result<()> {
+    let metadata = cargo::metadata::metadata_with_args(["--no-deps"])?;
+
+    // A gallery of examples doesn't need a single binary
+    let is_gallery =
+        matches!(&args.subcommand, Some(RunSubcommands::Web(web_args)) if web_args.examples);
+    if !is_gallery {
+        let package_selection = args.cargo_args.package_selection()?;
+        if let Some(run_target) = pick_run_target(
+            &metadata,
+            &package_selection,
+            args.cargo_args.target_args.bin.as_deref(),
+            args.cargo_args.target_args.example.as_deref(),
+            args.skip_prompts,
+        )? {
+            let target_name = Some(run_target.target.name.clone());
+            args.cargo_args.package_args.package = Some(run_target.package.name.clone());
+            if run_target.is_example {
+                args.cargo_args.target_args.example = target_name;
+            } else {
+                args.cargo_args.target_args.bin = target_name;
+            }
+        }
+    }
+
+    let package = CliConfig::select_package(
+        &metadata,
+        &args.cargo_args.package_selection()?,
+        args.cargo_args.target_args.bin.as_deref(),
+        args.cargo_args.target_args.example.as_deref(),
+    )?;
+    let config = CliConfig::for_package(&metadata, package, args.is_web())?;
+    args.apply_config(&config);
+
+    // Without a selected package, the folders of the workspace root are watched
+    let folders = PackageFolders::resolve(
+        package
+            .and_then(|package| package.manifest_path.parent())
+            .unwrap_or(&metadata.workspace_root),
+        config.assets_path.as_deref(),
+        config.web_path.as_deref(),
+    );
 
-pub fn run(args: &RunArgs) -> anyhow::Result<()> {
     if let Some(RunSubcommands::Web(web_args)) = &args.subcommand {
         let mut build_args = args.clone().into();
-        let web_bundle = build_web(&mut build_args)?;
+        let web_bundle = build_web(&mut build_args, &metadata)?;
 
-        let port = web_args.port;
-        let url = format!("http://localhost:{port}");
+        let tls_config = if web_args.is_https() {
+            Some(tls::server_config(web_args, &metadata.target_directory)?)
+        } else {
+            None
+        };
+
+        let scheme = if tls_config.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        let port = web_args.port();
+        let urls: Vec<_> = serve::reachable_hosts(web_args.host())
+            .iter()
+            .map(|host| app_url(scheme, host, port, &web_args.app_args))
+            .collect();
+        let url = &urls[0];
 
         // Serving the app is blocking, so we open the page first
-        if web_args.open {
-            match webbrowser::open(&url) {
+        if web_args.open.unwrap_or_default() {
+            match webbrowser::open(url) {
                 Ok(()) => println!("Your app is running at <{url}>!"),
                 Err(error) => {
                     println!("Failed to open the browser automatically, open the app at <{url}>. (Error: {error:?}")
@@ -35,56 +113,273 @@ pub fn run(args: &RunArgs) -> anyhow::Result<()> {
             println!("Open your app at <{url}>!");
         }
 
-        serve::serve(web_bundle, port)?;
+        if urls.len() > 1 {
+            println!("It's also reachable in your network at:");
+            for url in &urls[1..] {
+                println!("- <{url}>");
+            }
+        }
+
+        if web_args.show_qr_code {
+            // Other devices need an address in the network, `localhost` won't work for them
+            print_qr_code(urls.get(1).unwrap_or(url))?;
+        }
+
+        let web_bundle = Arc::new(RwLock::new(web_bundle));
+
+        let live_reload = if args.watch {
+            let watcher = FileWatcher::new(
+                watched_paths(&metadata, &folders),
+                &ignored_paths(args, &metadata),
+            )?;
+            let live_reload = LiveReload::new();
+
+            thread::spawn({
+                let args = args.clone();
+                let web_bundle = web_bundle.clone();
+                let live_reload = live_reload.clone();
+                move || rebuild_web_on_change(&args, &watcher, &web_bundle, &live_reload)
+            });
+
+            println!("Watching for changes...");
+            Some(live_reload)
+        } else {
+            None
+        };
+
+        serve::serve(
+            web_bundle,
+            web_args.host(),
+            port,
+            tls_config,
+            web_args.is_multithreaded.unwrap_or_default(),
+            live_reload,
+        )?;
+    } else if args.watch {
+        let watcher = FileWatcher::new(
+            watched_paths(&metadata, &folders),
+            &ignored_paths(args, &metadata),
+        )?;
+        run_native_on_change(args, &metadata, &watcher)?;
     } else {
-        let cargo_args = args.cargo_args_builder();
         // For native builds, wrap `cargo run`
-        cargo::run::command().args(cargo_args).ensure_status()?;
+        native_command(cargo::run::command(), args)
+            .args(args.cargo_args.app_args_builder())
+            .ensure_status()?;
+    }
+
+    Ok(())
+}
+
+/// The URL under which the app is served.
+///
+/// The arguments for the app are passed as `arg` parameters in the query string.
+fn app_url(scheme: &str, host: &str, port: u16, app_args: &[String]) -> String {
+    let url = format!("{scheme}://{host}:{port}");
+
+    if app_args.is_empty() {
+        return url;
     }
 
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(app_args.iter().map(|arg| ("arg", arg)))
+        .finish();
+    format!("{url}/?{query}")
+}
+
+/// Print a QR code of the URL in the terminal.
+fn print_qr_code(url: &str) -> anyhow::Result<()> {
+    let qr_code = QrCode::new(url).context("failed to create QR code")?;
+
+    // Most terminals have a dark background, so the colors are inverted
+    let rendered = qr_code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build();
+    println!("{rendered}");
+
     Ok(())
 }
 
+/// The paths to watch for changes when using `--watch`.
+///
+/// This includes the whole workspace, as well as the assets and web folders of the package,
+/// which can be outside of the workspace.
+fn watched_paths(metadata: &Metadata, folders: &PackageFolders) -> [PathBuf; 3] {
+    [
+        metadata.workspace_root.clone(),
+        folders.assets.clone(),
+        folders.web.clone(),
+    ]
+}
+
+/// The paths which are written by the build and must not trigger a rebuild with `--watch`.
+///
+/// Cargo compiles into the directory of `--target-dir`, if given, while the CLI always places
+/// its bundles and certificates in the target directory of the metadata.
+fn ignored_paths(args: &RunArgs, metadata: &Metadata) -> Vec<PathBuf> {
+    vec![
+        args.cargo_args.compilation_args.target_directory(metadata),
+        metadata.target_directory.clone(),
+    ]
+}
+
+/// Add the arguments and `rustc` flags for native builds to the given `cargo` command.
+fn native_command(mut command: Command, args: &RunArgs) -> Command {
+    command.args(args.cargo_args_builder());
+    args.cargo_args
+        .compilation_args
+        .apply_rustflags(&mut command);
+    command
+}
+
+/// Run the app natively and restart it whenever the watched files change.
+///
+/// The app is only restarted if the new build succeeded, otherwise the previous version keeps
+/// running. On platforms where running executables are locked, the app is stopped before the
+/// build instead.
+///
+/// The compiled binary is started directly instead of via `cargo run`, so that the app itself
+/// can be stopped.
+fn run_native_on_change(
+    args: &RunArgs,
+    metadata: &Metadata,
+    watcher: &FileWatcher,
+) -> anyhow::Result<()> {
+    let binary = NativeBinary::select(metadata, args)?;
+    let mut app: Option<Child> = None;
+
+    loop {
+        if native::LOCKS_RUNNING_EXECUTABLES {
+            if let Some(previous_app) = app.take() {
+                native::stop(previous_app)?;
+            }
+        }
+
+        // The app is compiled separately, so that it can keep running until the build succeeded.
+        // Errors are already reported by `cargo` itself.
+        if native_command(cargo::build::command(), args)
+            .ensure_status()
+            .is_ok()
+        {
+            if let Some(previous_app) = app.take() {
+                native::stop(previous_app)?;
+            }
+
+            // The app inherits the terminal, so its output is streamed as usual
+            app = Some(binary.spawn(&args.cargo_args.app_args)?);
+        }
+
+        watcher.wait_for_change()?;
+        println!("Detected changes, restarting the app...");
+    }
+}
+
+/// Rebuild the app for the web whenever the watched files change.
+///
+/// After a successful build, the served bundle is replaced and the browsers reload the page.
+fn rebuild_web_on_change(
+    args: &RunArgs,
+    watcher: &FileWatcher,
+    web_bundle: &RwLock<WebBundle>,
+    live_reload: &LiveReload,
+) {
+    loop {
+        if let Err(error) = watcher.wait_for_change() {
+            eprintln!("Stopped watching for changes: {error:?}");
+            return;
+        }
+
+        println!("Detected changes, rebuilding...");
+
+        // Start from the original arguments, as building the app modifies them
+        let mut build_args: BuildArgs = args.clone().into();
+        let result = cargo::metadata::metadata_with_args(["--no-deps"])
+            .and_then(|metadata| build_web(&mut build_args, &metadata));
+
+        match result {
+            Ok(new_bundle) => {
+                *web_bundle.write().unwrap_or_else(PoisonError::into_inner) = new_bundle;
+                live_reload.reload();
+                println!("Rebuilt the app, reloading the browser.");
+            }
+            Err(error) => eprintln!("Failed to rebuild the app: {error:?}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct BinTarget {
     /// The path to the directory in `target` which contains the binary.
     pub(crate) artifact_directory: PathBuf,
     /// The name of the binary (without any extensions).
     pub(crate) bin_name: String,
+    /// The name of the package containing the binary.
+    pub(crate) package_name: String,
+    /// The version of the package containing the binary.
+    pub(crate) package_version: String,
+}
+
+/// A binary or example target, together with the package it is defined in.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RunTarget<'a> {
+    /// The package containing the target.
+    pub(crate) package: &'a Package,
+    /// The binary or example target.
+    pub(crate) target: &'a Target,
+    /// Whether the target is an example.
+    pub(crate) is_example: bool,
 }
 
 /// Determine which binary target should be run.
 ///
-/// The `--package` arg narrows down the search space to the given package,
-/// while the `--bin` and `--example` args determine the binary target within the selected packages.
-///
-/// If the search couldn't be narrowed down to a single binary,
-/// the `default_run` option is taken into account.
+/// See [`select_run_target`] for the selection rules.
 ///
 /// The path to the compiled binary is determined via the compilation target and profile.
 pub(crate) fn select_run_binary(
     metadata: &Metadata,
-    package_name: Option<&str>,
+    package_selection: &PackageSelection,
     bin_name: Option<&str>,
     example_name: Option<&str>,
     compile_target: Option<&str>,
     compile_profile: &str,
 ) -> anyhow::Result<BinTarget> {
-    // Determine which packages the binary could be in
-    let packages = if let Some(package_name) = package_name {
-        let package = metadata
-            .packages
-            .iter()
-            .find(|package| package.name == *package_name)
-            .ok_or_else(|| anyhow::anyhow!("Failed to find package {package_name}"))?;
-        vec![package]
-    } else {
-        metadata.packages.iter().collect()
-    };
+    let run_target = select_run_target(metadata, package_selection, bin_name, example_name)?;
+
+    // Assemble the path where the binary will be put
+    let artifact_directory = get_artifact_directory(
+        metadata.target_directory.clone(),
+        compile_target,
+        compile_profile,
+        run_target.is_example,
+    );
 
+    Ok(BinTarget {
+        bin_name: run_target.target.name.clone(),
+        package_name: run_target.package.name.clone(),
+        package_version: run_target.package.version.to_string(),
+        artifact_directory,
+    })
+}
+
+/// Determine which binary or example target should be run.
+///
+/// The packages are selected like Cargo does, see [`Metadata::selected_packages`],
+/// while the `--bin` and `--example` args determine the binary target within the selected packages.
+///
+/// If the search couldn't be narrowed down to a single binary,
+/// the `default_run` option is taken into account.
+pub(crate) fn select_run_target<'a>(
+    metadata: &'a Metadata,
+    package_selection: &PackageSelection,
+    bin_name: Option<&str>,
+    example_name: Option<&str>,
+) -> anyhow::Result<RunTarget<'a>> {
+    let packages = metadata.selected_packages(package_selection)?;
     let mut is_example = false;
 
-    let target = if let Some(bin_name) = bin_name {
+    let (package, target) = if let Some(bin_name) = bin_name {
         // The user specified a concrete binary
         let bins: Vec<_> = packages
             .iter()
@@ -92,6 +387,7 @@ pub(crate) fn select_run_binary(
                 package
                     .bin_targets()
                     .filter(|target| target.name == *bin_name)
+                    .map(move |target| (*package, target))
             })
             .collect();
 
@@ -110,6 +406,7 @@ pub(crate) fn select_run_binary(
                 package
                     .example_targets()
                     .filter(|target| target.name == *example_name)
+                    .map(move |target| (*package, target))
             })
             .collect();
 
@@ -123,55 +420,59 @@ pub(crate) fn select_run_binary(
         examples[0]
     } else {
         // Nothing concrete specified, try to pick one automatically
+        default_run_target(&packages)?.ok_or_else(|| {
+            anyhow::anyhow!("There are multiple binaries available, try specifying one with --bin or define `default_run` in the Cargo.toml")
+        })?
+    };
 
-        // If there is only one binary, pick that one
-        let bins: Vec<_> = packages
-            .iter()
-            .flat_map(|package| package.bin_targets())
-            .collect();
+    Ok(RunTarget {
+        package,
+        target,
+        is_example,
+    })
+}
 
-        if bins.is_empty() {
-            anyhow::bail!("No binaries available!");
-        } else if bins.len() == 1 {
-            bins[0]
-        } else {
-            // Otherwise, check if there is a default run target defined
-            let default_runs: Vec<_> = packages
-                .iter()
-                .filter_map(|package| package.default_run.as_ref())
-                .collect();
-
-            if default_runs.is_empty() {
-                anyhow::bail!("There are multiple binaries available, try specifying one with --bin or define `default_run` in the Cargo.toml");
-            } else if default_runs.len() > 1 {
-                anyhow::bail!(
-                    "Found multiple `default_run` definitions, I don't know which one to pick!"
-                );
-            }
+/// Pick the binary to run if none has been specified.
+///
+/// This is the only binary of the packages, or otherwise the `default_run` binary.
+/// Returns `None` if there are multiple binaries and none of them is the `default_run` binary.
+fn default_run_target<'a>(
+    packages: &[&'a Package],
+) -> anyhow::Result<Option<(&'a Package, &'a Target)>> {
+    // If there is only one binary, pick that one
+    let bins: Vec<_> = packages
+        .iter()
+        .flat_map(|package| package.bin_targets().map(move |target| (*package, target)))
+        .collect();
+
+    if bins.is_empty() {
+        anyhow::bail!("No binaries available!");
+    } else if bins.len() == 1 {
+        return Ok(Some(bins[0]));
+    }
 
-            let default_run = default_runs[0];
-            bins.iter()
-                .find(|bin| bin.name == *default_run)
-                .ok_or_else(|| anyhow::anyhow!("Didn't find `default_run` binary {default_run}"))?
-        }
-    };
+    // Otherwise, check if there is a default run target defined
+    let default_runs: Vec<_> = packages
+        .iter()
+        .filter_map(|package| package.default_run.as_ref())
+        .collect();
 
-    // Assemble the path where the binary will be put
-    let artifact_directory = get_artifact_directory(
-        metadata.target_directory.clone(),
-        compile_target,
-        compile_profile,
-        is_example,
-    );
+    if default_runs.is_empty() {
+        return Ok(None);
+    } else if default_runs.len() > 1 {
+        anyhow::bail!("Found multiple `default_run` definitions, I don't know which one to pick!");
+    }
 
-    Ok(BinTarget {
-        bin_name: target.name.clone(),
-        artifact_directory,
-    })
+    let default_run = default_runs[0];
+    let bin = bins
+        .iter()
+        .find(|(_, bin)| bin.name == *default_run)
+        .ok_or_else(|| anyhow::anyhow!("Didn't find `default_run` binary {default_run}"))?;
+    Ok(Some(*bin))
 }
 
 /// Determine the path to the directory which contains the compilation artifacts.
-fn get_artifact_directory(
+pub(crate) fn get_artifact_directory(
     target_directory: impl Into<PathBuf>,
     target: Option<&str>,
     profile: &str,
@@ -200,8 +501,107 @@ fn get_artifact_directory(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::external_cli::cargo::metadata::fixture::TestWorkspace;
     use std::path::Path;
 
+    /// A workspace with a `game` package containing two binaries and an `editor` package with
+    /// an example.
+    pub(super) fn metadata() -> Metadata {
+        TestWorkspace::new()
+            .package("game", "game", &[("bin", "game"), ("bin", "tool")])
+            .package(
+                "editor",
+                "editor",
+                &[("lib", "editor"), ("example", "demo")],
+            )
+            .build()
+    }
+
+    /// The package selection when running the command in the given directory.
+    fn in_dir(dir: &str) -> PackageSelection<'static> {
+        PackageSelection {
+            manifest_dir: PathBuf::from(dir),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_run_target_package() {
+        let metadata = metadata();
+
+        let run_target =
+            select_run_target(&metadata, &in_dir("/workspace"), Some("tool"), None).unwrap();
+        assert_eq!(run_target.package.name, "game");
+        assert_eq!(run_target.target.name, "tool");
+        assert!(!run_target.is_example);
+
+        let run_target =
+            select_run_target(&metadata, &in_dir("/workspace"), None, Some("demo")).unwrap();
+        assert_eq!(run_target.package.name, "editor");
+        assert!(run_target.is_example);
+    }
+
+    #[test]
+    fn test_run_target_ambiguous() {
+        let metadata = metadata();
+
+        assert!(select_run_target(&metadata, &in_dir("/workspace"), None, None).is_err());
+        let editor = PackageSelection {
+            package: Some("editor"),
+            ..in_dir("/workspace")
+        };
+        assert!(select_run_target(&metadata, &editor, Some("tool"), None).is_err());
+    }
+
+    #[test]
+    fn test_run_target_current_package() {
+        let metadata = metadata();
+
+        // Like `cargo run`, only the package in the current directory is searched
+        let run_target = select_run_target(
+            &metadata,
+            &in_dir("/workspace/editor/examples"),
+            None,
+            Some("demo"),
+        )
+        .unwrap();
+        assert_eq!(run_target.package.name, "editor");
+        assert!(
+            select_run_target(&metadata, &in_dir("/workspace/game"), None, Some("demo")).is_err()
+        );
+
+        let workspace = PackageSelection {
+            is_workspace: true,
+            ..in_dir("/workspace/game")
+        };
+        assert!(select_run_target(&metadata, &workspace, None, Some("demo")).is_ok());
+    }
+
+    #[test]
+    fn test_app_url_without_args() {
+        assert_eq!(
+            app_url("http", "localhost", 4000, &[]),
+            "http://localhost:4000"
+        );
+    }
+
+    #[test]
+    fn test_app_url_https() {
+        assert_eq!(
+            app_url("https", "192.168.0.2", 4000, &[]),
+            "https://192.168.0.2:4000"
+        );
+    }
+
+    #[test]
+    fn test_app_url_with_args() {
+        let args = ["--level".to_owned(), "3".to_owned(), "a b".to_owned()];
+        assert_eq!(
+            app_url("http", "localhost", 4000, &args),
+            "http://localhost:4000/?arg=--level&arg=3&arg=a+b"
+        );
+    }
+
     #[test]
     fn test_artifact_directory_dev_native() {
         let actual = get_artifact_directory(Path::new("/target"), None, "dev", false);
@@ -0,0 +1,172 @@
+//! Running the compiled app natively, without going through `cargo run`.
+//!
+//! This gives full control over the app process, which is needed to restart it on changes.
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::{Child, Command},
+};
+#[cfg(unix)]
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context as _;
+
+use super::{get_artifact_directory, select_run_target, RunArgs};
+use crate::external_cli::cargo::metadata::Metadata;
+
+/// How long to wait for the app to exit on its own, before it is killed.
+#[cfg(unix)]
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Whether running executables can't be overwritten, so the app needs to be stopped before
+/// rebuilding it.
+pub(crate) const LOCKS_RUNNING_EXECUTABLES: bool = cfg!(windows);
+
+/// The compiled binary of the app.
+#[derive(Debug, Clone)]
+pub(crate) struct NativeBinary {
+    /// The path to the executable.
+    path: PathBuf,
+    /// The directory of the package containing the binary.
+    package_directory: PathBuf,
+    /// The directory of the compilation profile, e.g. `target/debug`.
+    profile_directory: PathBuf,
+}
+
+impl NativeBinary {
+    /// Determine the binary which `cargo build` creates for the given arguments.
+    pub(crate) fn select(metadata: &Metadata, args: &RunArgs) -> anyhow::Result<Self> {
+        let run_target = select_run_target(
+            metadata,
+            &args.cargo_args.package_selection()?,
+            args.cargo_args.target_args.bin.as_deref(),
+            args.cargo_args.target_args.example.as_deref(),
+        )?;
+
+        let compilation_args = &args.cargo_args.compilation_args;
+        let profile_directory = get_artifact_directory(
+            compilation_args.target_directory(metadata),
+            compilation_args.target(false).as_deref(),
+            compilation_args.profile(false),
+            false,
+        );
+
+        let mut path = if run_target.is_example {
+            profile_directory.join("examples")
+        } else {
+            profile_directory.clone()
+        };
+        path.push(format!(
+            "{}{}",
+            run_target.target.name,
+            env::consts::EXE_SUFFIX
+        ));
+
+        Ok(Self {
+            path,
+            package_directory: run_target
+                .package
+                .manifest_path
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+            profile_directory,
+        })
+    }
+
+    /// Start the app with the given arguments.
+    ///
+    /// The environment is set up like `cargo run` does, so that Bevy finds the assets of the
+    /// package and the dynamic libraries used by the `dynamic_linking` feature.
+    pub(crate) fn spawn(&self, app_args: &[String]) -> anyhow::Result<Child> {
+        let mut command = Command::new(&self.path);
+        command
+            .args(app_args)
+            .env("CARGO_MANIFEST_DIR", &self.package_directory);
+
+        let (variable, search_path) = self.library_search_path();
+        if let Ok(search_path) = search_path {
+            command.env(variable, search_path);
+        }
+
+        command
+            .spawn()
+            .with_context(|| format!("failed to start {}", self.path.display()))
+    }
+
+    /// The environment variable for the search path of dynamic libraries and its new value.
+    ///
+    /// Like `cargo run`, the dependencies and the libraries of the toolchain are added in front of
+    /// the existing search path.
+    fn library_search_path(&self) -> (&'static str, Result<OsString, env::JoinPathsError>) {
+        let variable = if cfg!(windows) {
+            "PATH"
+        } else if cfg!(target_os = "macos") {
+            "DYLD_FALLBACK_LIBRARY_PATH"
+        } else {
+            "LD_LIBRARY_PATH"
+        };
+
+        let mut paths = vec![
+            self.profile_directory.join("deps"),
+            self.profile_directory.clone(),
+        ];
+        paths.extend(toolchain_library_directory());
+        if let Some(existing) = env::var_os(variable) {
+            paths.extend(env::split_paths(&existing));
+        }
+
+        (variable, env::join_paths(paths))
+    }
+}
+
+/// The directory containing the libraries of the toolchain, like the standard library.
+fn toolchain_library_directory() -> Option<PathBuf> {
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .args(["--print", "target-libdir"])
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
+/// Stop the running app and wait until it exited.
+///
+/// On Unix, the app is asked to exit first and only killed if it doesn't exit in time.
+/// On other platforms, the app is killed right away.
+pub(crate) fn stop(mut app: Child) -> anyhow::Result<()> {
+    // The app might have exited already
+    if app.try_wait()?.is_some() {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    {
+        let pid = libc::pid_t::try_from(app.id()).context("invalid process ID")?;
+        // SAFETY: The process has not been waited for yet, so the ID still refers to the app
+        unsafe { libc::kill(pid, libc::SIGTERM) };
+
+        let start = Instant::now();
+        while start.elapsed() < STOP_TIMEOUT {
+            if app.try_wait()?.is_some() {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        println!("The app didn't exit in time, killing it...");
+    }
+
+    // The app might have exited in the meantime, in which case there is nothing to kill
+    let _ = app.kill();
+    app.wait().context("failed to stop the app")?;
+    Ok(())
+}
@@ -0,0 +1,207 @@
+//! Letting the user pick the binary or example to run, if the selection is ambiguous.
+use std::{
+    env, fs,
+    io::{self, IsTerminal as _},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use dialoguer::Select;
+use serde::{Deserialize, Serialize};
+
+use crate::external_cli::cargo::metadata::{Metadata, PackageSelection};
+
+use super::{default_run_target, RunTarget};
+
+/// The target picked the last time, identified independently of the metadata.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct PickedTarget {
+    package: String,
+    name: String,
+    is_example: bool,
+}
+
+impl From<&RunTarget<'_>> for PickedTarget {
+    fn from(run_target: &RunTarget<'_>) -> Self {
+        Self {
+            package: run_target.package.name.clone(),
+            name: run_target.target.name.clone(),
+            is_example: run_target.is_example,
+        }
+    }
+}
+
+/// Let the user pick the binary or example to run, if neither `--bin` nor `--example` is given
+/// and there is no single or `default_run` binary.
+///
+/// The picker lists all binaries and examples of the selected packages. It is only shown in
+/// interactive terminals, so `None` is returned in CI or with `--yes`, where
+/// [`select_run_target`](super::select_run_target) reports the ambiguity instead.
+///
+/// The picked target is remembered in the target directory and preselected the next time.
+pub(crate) fn pick_run_target<'a>(
+    metadata: &'a Metadata,
+    package_selection: &PackageSelection,
+    bin_name: Option<&str>,
+    example_name: Option<&str>,
+    skip_prompts: bool,
+) -> anyhow::Result<Option<RunTarget<'a>>> {
+    if bin_name.is_some() || example_name.is_some() || !is_interactive(skip_prompts) {
+        return Ok(None);
+    }
+
+    let packages = metadata.selected_packages(package_selection)?;
+    // Other errors, like missing binaries, are reported when the target is selected
+    if !matches!(default_run_target(&packages), Ok(None)) {
+        return Ok(None);
+    }
+
+    let candidates: Vec<_> = packages
+        .iter()
+        .flat_map(|package| {
+            let bins = package.bin_targets().map(move |target| RunTarget {
+                package,
+                target,
+                is_example: false,
+            });
+            let examples = package.example_targets().map(move |target| RunTarget {
+                package,
+                target,
+                is_example: true,
+            });
+            bins.chain(examples)
+        })
+        .collect();
+    let labels: Vec<_> = candidates.iter().map(label).collect();
+
+    let path = picked_target_path(metadata);
+    let picked = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok());
+
+    let index = Select::new()
+        .with_prompt("There are multiple binaries available, which one should run?")
+        .items(&labels)
+        .default(default_index(&candidates, picked.as_ref()))
+        .interact()?;
+    let run_target = candidates[index];
+
+    // Not remembering the choice shouldn't prevent running the app
+    if let Err(error) = remember(&path, &run_target) {
+        eprintln!("Failed to remember the selected binary: {error:?}");
+    }
+
+    Ok(Some(run_target))
+}
+
+/// Whether the user can be asked to pick a target.
+fn is_interactive(skip_prompts: bool) -> bool {
+    !skip_prompts && env::var_os("CI").is_none() && io::stdin().is_terminal()
+}
+
+/// The file storing the last picked target of the workspace.
+fn picked_target_path(metadata: &Metadata) -> PathBuf {
+    metadata
+        .target_directory
+        .join("bevy_cli")
+        .join("picked_target.json")
+}
+
+/// Store the picked target, to preselect it the next time.
+fn remember(path: &Path, run_target: &RunTarget) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(
+        path,
+        serde_json::to_string(&PickedTarget::from(run_target))?,
+    )
+    .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// The entry of the target in the picker.
+fn label(run_target: &RunTarget) -> String {
+    let kind = if run_target.is_example {
+        "example"
+    } else {
+        "bin"
+    };
+    format!(
+        "{} ({kind} in {})",
+        run_target.target.name, run_target.package.name
+    )
+}
+
+/// The index of the previously picked target, or the first one if it doesn't exist anymore.
+fn default_index(candidates: &[RunTarget], picked: Option<&PickedTarget>) -> usize {
+    picked
+        .and_then(|picked| {
+            candidates
+                .iter()
+                .position(|candidate| PickedTarget::from(candidate) == *picked)
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run::tests::metadata;
+
+    use super::*;
+
+    fn candidates(metadata: &Metadata) -> Vec<RunTarget<'_>> {
+        let game = &metadata.packages[0];
+        let editor = &metadata.packages[1];
+        vec![
+            RunTarget {
+                package: game,
+                target: &game.targets[0],
+                is_example: false,
+            },
+            RunTarget {
+                package: editor,
+                target: &editor.targets[1],
+                is_example: true,
+            },
+        ]
+    }
+
+    #[test]
+    fn should_label_bins_and_examples() {
+        let metadata = metadata();
+        let labels: Vec<_> = candidates(&metadata).iter().map(label).collect();
+        assert_eq!(labels, ["game (bin in game)", "demo (example in editor)"]);
+    }
+
+    #[test]
+    fn should_preselect_previously_picked_target() {
+        let metadata = metadata();
+        let candidates = candidates(&metadata);
+        let picked = PickedTarget {
+            package: "editor".to_owned(),
+            name: "demo".to_owned(),
+            is_example: true,
+        };
+        assert_eq!(default_index(&candidates, Some(&picked)), 1);
+
+        let removed = PickedTarget {
+            name: "removed".to_owned(),
+            ..picked
+        };
+        assert_eq!(default_index(&candidates, Some(&removed)), 0);
+        assert_eq!(default_index(&candidates, None), 0);
+    }
+
+    #[test]
+    fn should_remember_picked_target() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("bevy_cli/picked_target.json");
+        let metadata = metadata();
+        let candidates = candidates(&metadata);
+
+        remember(&path, &candidates[1]).unwrap();
+        let picked: PickedTarget =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(default_index(&candidates, Some(&picked)), 1);
+    }
+}
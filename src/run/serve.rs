@@ -1,72 +1,573 @@
 //! Serving the app locally for the browser.
-use actix_web::{rt, web, App, HttpResponse, HttpServer, Responder};
+use std::{
+    convert::Infallible,
+    env, fs,
+    io::{self, IsTerminal as _},
+    net::IpAddr,
+    path::{Component, Path},
+    sync::{Arc, PoisonError, RwLock},
+};
 
-use crate::web::bundle::{Index, LinkedBundle, PackedBundle, WebBundle};
+use actix_files::NamedFile;
+use actix_web::{
+    dev::Service as _,
+    http::header::{self, ContentEncoding, Encoding, HeaderValue},
+    middleware::{Condition, DefaultHeaders},
+    rt,
+    web::{self, Bytes},
+    App, HttpMessage as _, HttpRequest, HttpResponse, HttpServer,
+};
+use futures_util::{
+    future::{self, Either},
+    stream,
+};
+use rustls::ServerConfig;
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::web::{
+    bundle::{Index, LinkedBundle, PackedBundle, WebBundle},
+    compression, index,
+    threads::CROSS_ORIGIN_ISOLATION_HEADERS,
+};
+
+/// The script reloading the page when the app has been rebuilt.
+const LIVE_RELOAD_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/assets/web/live_reload.js"
+));
+
+/// The script sending the browser console output to the server.
+const CONSOLE_FORWARDING_SCRIPT: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/assets/web/console_forwarding.js"
+));
+
+/// The maximum size of a forwarded log entry, large enough for long stack traces.
+const MAX_LOG_SIZE: usize = 1024 * 1024;
+
+/// Notifies all connected browsers that the app should be reloaded.
+#[derive(Debug, Clone)]
+pub(crate) struct LiveReload(broadcast::Sender<()>);
+
+impl LiveReload {
+    pub(crate) fn new() -> Self {
+        Self(broadcast::channel(1).0)
+    }
+
+    /// Tell all connected browsers to reload the page.
+    pub(crate) fn reload(&self) {
+        // An error only means that no browser is connected, so there is nothing to reload
+        let _ = self.0.send(());
+    }
+}
+
+/// An entry of the browser console, see `console_forwarding.js`.
+#[derive(Debug, Deserialize)]
+struct BrowserLog {
+    /// The name of the console method, e.g. `warn`.
+    level: String,
+    /// The formatted message.
+    message: String,
+}
+
+/// Serve an HTML page of the current web bundle, `index.html` if the route doesn't name one.
+///
+/// The script for forwarding the console output is injected into the page,
+/// as well as the script for live reloading if it's enabled.
+async fn serve_page(
+    req: HttpRequest,
+    web_bundle: web::Data<RwLock<WebBundle>>,
+    live_reload: Option<web::Data<LiveReload>>,
+) -> actix_web::Result<HttpResponse> {
+    let page = req.match_info().get("page").unwrap_or("index.html");
+    let mut content = page_content(
+        &web_bundle.read().unwrap_or_else(PoisonError::into_inner),
+        page,
+    )?;
+    content = inject_script(&content, CONSOLE_FORWARDING_SCRIPT);
+
+    if live_reload.is_some() {
+        content = inject_script(&content, LIVE_RELOAD_SCRIPT);
+    }
+
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/html; charset=utf-8"))
+        .body(content))
+}
+
+/// Stream an event to the browser whenever the app should be reloaded.
+///
+/// This uses [server-sent events](https://developer.mozilla.org/en-US/docs/Web/API/Server-sent_events).
+async fn reload_events(live_reload: web::Data<LiveReload>) -> HttpResponse {
+    let events = stream::unfold(live_reload.0.subscribe(), |mut receiver| async move {
+        match receiver.recv().await {
+            // If events have been missed, the page still needs to be reloaded
+            Ok(()) | Err(RecvError::Lagged(_)) => Some((
+                Ok::<_, Infallible>(Bytes::from_static(b"event: reload\ndata:\n\n")),
+                receiver,
+            )),
+            Err(RecvError::Closed) => None,
+        }
+    });
 
-/// Serve a static HTML file with the given content.
-async fn serve_static_html(content: &'static str) -> impl Responder {
-    // Build the HTTP response with appropriate headers to serve the content as a file
     HttpResponse::Ok()
-        .insert_header((
-            actix_web::http::header::CONTENT_TYPE,
-            "text/html; charset=utf-8",
-        ))
-        .body(content)
+        .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events)
+}
+
+/// Print an entry of the browser console in the terminal.
+///
+/// Only entries sent by pages of the served origin are accepted, because the server can be
+/// reachable in the local network.
+async fn print_browser_log(req: HttpRequest, log: web::Json<BrowserLog>) -> HttpResponse {
+    let origin = req
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|origin| origin.to_str().ok());
+    if !is_same_origin(origin, req.connection_info().host()) {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    let use_colors = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+    println!(
+        "{} {}",
+        format_level(&log.level, use_colors),
+        strip_control_characters(&log.message)
+    );
+
+    HttpResponse::NoContent().finish()
+}
+
+/// Check if the `Origin` header of a request matches the host it was sent to.
+///
+/// Browsers always send the header for `POST` requests, so requests without it are rejected.
+fn is_same_origin(origin: Option<&str>, host: &str) -> bool {
+    origin
+        .and_then(|origin| origin.split_once("://"))
+        .is_some_and(|(_, origin_host)| origin_host.eq_ignore_ascii_case(host))
+}
+
+/// Remove control characters from a message, except for line breaks and tabs.
+///
+/// This prevents the message from injecting escape sequences into the terminal.
+fn strip_control_characters(message: &str) -> String {
+    message
+        .chars()
+        .filter(|char| !char.is_control() || matches!(char, '\n' | '\t'))
+        .collect()
+}
+
+/// Format the level of a browser console entry, similar to the native log output.
+fn format_level(level: &str, use_colors: bool) -> String {
+    // ANSI color codes: red, yellow, green, blue and white
+    let (label, color) = match level {
+        "error" => ("ERROR", 31),
+        "warn" => (" WARN", 33),
+        "info" => (" INFO", 32),
+        "debug" => ("DEBUG", 34),
+        _ => ("  LOG", 37),
+    };
+
+    if use_colors {
+        format!("\x1b[{color}m{label}\x1b[0m")
+    } else {
+        label.to_owned()
+    }
+}
+
+/// Open the precompressed variant of a file in the packed bundle, if it exists and the browser
+/// accepts its encoding.
+fn precompressed_file(req: &HttpRequest, bundle_path: &Path) -> Option<NamedFile> {
+    // Percent-encoded paths are not decoded here, the uncompressed file is served for them instead
+    let relative_path = Path::new(req.path().trim_start_matches('/'));
+    if !relative_path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let path = bundle_path.join(relative_path);
+    let available_encodings: Vec<Encoding> = compression::ENCODINGS
+        .iter()
+        .filter(|(_, extension)| compression::append_extension(&path, extension).is_file())
+        .filter_map(|(encoding, _)| encoding.parse().ok())
+        .chain([Encoding::identity()])
+        .collect();
+
+    let encoding = req
+        .get_header::<header::AcceptEncoding>()?
+        .negotiate(available_encodings.iter())?
+        .to_string();
+    let (_, extension) = compression::ENCODINGS
+        .iter()
+        .find(|(name, _)| *name == encoding)?;
+
+    // The content type is determined by the original file, not the compressed one
+    let content_type = actix_files::file_extension_to_mime(path.extension()?.to_str()?);
+    let file = NamedFile::open(compression::append_extension(&path, extension)).ok()?;
+
+    Some(
+        file.set_content_type(content_type)
+            .set_content_encoding(encoding.parse::<ContentEncoding>().ok()?)
+            .disable_content_disposition(),
+    )
+}
+
+/// Read the contents of an HTML page of the web bundle.
+///
+/// Linked bundles only consist of `index.html`, while packed bundles can contain more pages,
+/// e.g. the pages of the examples in a gallery.
+fn page_content(web_bundle: &WebBundle, page: &str) -> io::Result<String> {
+    // Only serve files directly in the bundle, e.g. not `..\\secret.html` on Windows
+    let mut components = Path::new(page).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return Err(io::ErrorKind::NotFound.into());
+    }
+
+    match web_bundle {
+        WebBundle::Packed(PackedBundle { path }) => fs::read_to_string(path.join(page)),
+        WebBundle::Linked(LinkedBundle { index, .. }) if page == "index.html" => {
+            Ok(index.contents().to_owned())
+        }
+        WebBundle::Linked(_) => Err(io::ErrorKind::NotFound.into()),
+    }
+}
+
+/// Insert a script at the end of the head of the given HTML document.
+///
+/// This way, the script runs before the app is started.
+fn inject_script(html: &str, script: &str) -> String {
+    index::insert_into_head(html, &format!("<script>\n{script}</script>\n"))
+}
+
+/// Determine the hosts under which the server can be reached, when it's bound to `host`.
+///
+/// The first entry is the one to use on this machine, which is `localhost` for the loopback
+/// address. If the server is bound to all interfaces, the addresses of the interfaces in the
+/// local network follow.
+pub(crate) fn reachable_hosts(host: &str) -> Vec<String> {
+    let Ok(ip) = host.parse::<IpAddr>() else {
+        // Probably a domain name, which is reachable as it is
+        return vec![host.to_owned()];
+    };
+
+    if ip.is_loopback() {
+        return vec!["localhost".to_owned()];
+    }
+
+    if !ip.is_unspecified() {
+        return vec![format_host(ip)];
+    }
+
+    let interface_ips = if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|interface| interface.ip())
+        // The server only listens on the interfaces of the same IP version
+        .filter(|interface_ip| {
+            !interface_ip.is_loopback() && interface_ip.is_ipv4() == ip.is_ipv4()
+        });
+
+    ["localhost".to_owned()]
+        .into_iter()
+        .chain(interface_ips.map(format_host))
+        .collect()
+}
+
+/// Format an IP address for use in a URL.
+fn format_host(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => ip.to_string(),
+        IpAddr::V6(ip) => format!("[{ip}]"),
+    }
 }
 
 /// Launch a web server running the Bevy app.
-pub(crate) fn serve(web_bundle: WebBundle, port: u16) -> anyhow::Result<()> {
-    rt::System::new().block_on(
-        HttpServer::new(move || {
-            let mut app = App::new();
+///
+/// The bundle can be replaced while the server is running, e.g. after rebuilding the app.
+/// If `tls_config` is set, the app is served via HTTPS.
+/// If `multithreaded` is set, the headers for cross-origin isolation are sent, which are required
+/// for Wasm threads.
+/// If `live_reload` is set, connected browsers can be told to reload the page.
+pub(crate) fn serve(
+    web_bundle: Arc<RwLock<WebBundle>>,
+    host: &str,
+    port: u16,
+    tls_config: Option<ServerConfig>,
+    multithreaded: bool,
+    live_reload: Option<LiveReload>,
+) -> anyhow::Result<()> {
+    let server = HttpServer::new(move || {
+        let mut headers = DefaultHeaders::new();
+        for header in CROSS_ORIGIN_ISOLATION_HEADERS {
+            headers = headers.add(header);
+        }
 
-            match web_bundle.clone() {
-                WebBundle::Packed(PackedBundle { path }) => {
-                    app = app.service(actix_files::Files::new("/", path).index_file("index.html"));
-                }
-                WebBundle::Linked(LinkedBundle {
-                    build_artifact_path,
-                    wasm_file_name,
-                    js_file_name,
-                    index,
-                    assets_path,
-                }) => {
-                    // Serve the build artifacts at the `/build/*` route
-                    // A custom `index.html` will have to call `/build/{bin_name}.js`
-                    app = app.service(
-                        actix_files::Files::new("/build", build_artifact_path)
-                            // This potentially includes artifacts which we will not need,
-                            // but we can't add the bin name to the check due to lifetime
-                            // requirements
-                            .path_filter(move |path, _| {
-                                path.file_name() == Some(&js_file_name)
-                                    || path.file_name() == Some(&wasm_file_name)
-                            }),
-                    );
-
-                    // If the app has an assets folder, serve it under `/assets`
-                    if let Some(assets_path) = assets_path {
-                        app = app.service(actix_files::Files::new("/assets", assets_path))
-                    }
-
-                    match index {
-                        Index::Folder(path) => {
-                            app = app.service(
-                                actix_files::Files::new("/", path).index_file("index.html"),
-                            );
-                        }
-                        Index::Static(contents) => {
-                            app = app.route("/", web::get().to(move || serve_static_html(contents)))
+        let mut app = App::new()
+            .wrap(Condition::new(multithreaded, headers))
+            .app_data(web::Data::from(web_bundle.clone()))
+            .app_data(web::JsonConfig::default().limit(MAX_LOG_SIZE))
+            .route("/_bevy_cli/log", web::post().to(print_browser_log));
+
+        if let Some(live_reload) = &live_reload {
+            app = app
+                .app_data(web::Data::new(live_reload.clone()))
+                .route("/_bevy_cli/reload", web::get().to(reload_events));
+        }
+
+        // The build artifacts and assets keep their location when the app is rebuilt,
+        // so their services don't need to be updated
+        let current_bundle = web_bundle
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+
+        match current_bundle {
+            WebBundle::Packed(PackedBundle { path }) => {
+                let files = actix_files::Files::new("/", &path).index_file("index.html");
+
+                app = app
+                    .route("/", web::get().to(serve_page))
+                    // Packed bundles can contain other pages, like the examples of a gallery
+                    .route("/{page:[^/]+\\.html}", web::get().to(serve_page))
+                    .service(web::scope("").service(files).wrap_fn(move |req, service| {
+                        // Prefer the precompressed files, if the bundle has been precompressed
+                        match precompressed_file(req.request(), &path) {
+                            Some(file) => {
+                                let mut response = file.into_response(req.request());
+                                response.headers_mut().insert(
+                                    header::VARY,
+                                    HeaderValue::from_static("accept-encoding"),
+                                );
+                                Either::Left(future::ok(req.into_response(response)))
+                            }
+                            None => Either::Right(service.call(req)),
                         }
-                    }
+                    }));
+            }
+            WebBundle::Linked(LinkedBundle {
+                build_artifact_path,
+                wasm_file_name,
+                js_file_name,
+                index,
+                assets_path,
+            }) => {
+                // Serve the build artifacts at the `/build/*` route
+                // A custom `index.html` will have to call `/build/{bin_name}.js`
+                app = app.service(
+                    actix_files::Files::new("/build", build_artifact_path)
+                        // This potentially includes artifacts which we will not need,
+                        // but we can't add the bin name to the check due to lifetime
+                        // requirements
+                        .path_filter(move |path, _| {
+                            path.file_name() == Some(&js_file_name)
+                                || path.file_name() == Some(&wasm_file_name)
+                                // JS modules linked by the app
+                                || path.starts_with("snippets")
+                        }),
+                );
+
+                // If the app has an assets folder, serve it under `/assets`
+                if let Some(assets_path) = assets_path {
+                    app = app.service(actix_files::Files::new("/assets", assets_path))
+                }
+
+                app = app
+                    .route("/", web::get().to(serve_page))
+                    .route("/index.html", web::get().to(serve_page));
+
+                // Serve the other files of a custom web folder
+                if let Index::Folder { path, .. } = index {
+                    app = app.service(actix_files::Files::new("/", path).index_file("index.html"));
                 }
             }
+        }
 
-            app
-        })
-        .bind(("127.0.0.1", port))?
-        .run(),
-    )?;
+        app
+    })
+    // Connections for live reloading stay open, don't wait for them when shutting down
+    .shutdown_timeout(1);
+
+    let server = match tls_config {
+        Some(tls_config) => server.bind_rustls_0_23((host, port), tls_config)?,
+        None => server.bind((host, port))?,
+    };
+
+    rt::System::new().block_on(server.run())?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    /// Create a bundle with a precompressed JS file, a gzip-only Wasm file and an uncompressed
+    /// image.
+    fn precompressed_bundle() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path();
+        fs::write(path.join("app.js"), "js").unwrap();
+        fs::write(path.join("app.js.br"), "br").unwrap();
+        fs::write(path.join("app.js.gz"), "gz").unwrap();
+        fs::write(path.join("app_bg.wasm"), "wasm").unwrap();
+        fs::write(path.join("app_bg.wasm.gz"), "gz").unwrap();
+        fs::write(path.join("icon.png"), "png").unwrap();
+        temp_dir
+    }
+
+    /// The content encoding of the precompressed file served for the request, if any.
+    fn served_encoding(bundle_path: &Path, uri: &str, accept_encoding: &str) -> Option<String> {
+        let req = TestRequest::get()
+            .uri(uri)
+            .insert_header((header::ACCEPT_ENCODING, accept_encoding))
+            .to_http_request();
+        let response = precompressed_file(&req, bundle_path)?.into_response(&req);
+
+        response
+            .headers()
+            .get(header::CONTENT_ENCODING)
+            .map(|encoding| encoding.to_str().unwrap().to_owned())
+    }
+
+    #[test]
+    fn should_prefer_brotli() {
+        let bundle = precompressed_bundle();
+        assert_eq!(
+            served_encoding(bundle.path(), "/app.js", "gzip, deflate, br").as_deref(),
+            Some("br")
+        );
+    }
+
+    #[test]
+    fn should_serve_gzip_if_only_available_encoding() {
+        let bundle = precompressed_bundle();
+        assert_eq!(
+            served_encoding(bundle.path(), "/app.js", "gzip").as_deref(),
+            Some("gzip")
+        );
+        assert_eq!(
+            served_encoding(bundle.path(), "/app_bg.wasm", "gzip, br").as_deref(),
+            Some("gzip")
+        );
+    }
+
+    #[test]
+    fn should_serve_original_file_for_identity() {
+        let bundle = precompressed_bundle();
+        assert_eq!(served_encoding(bundle.path(), "/app.js", "identity"), None);
+        assert_eq!(
+            served_encoding(bundle.path(), "/icon.png", "gzip, br"),
+            None
+        );
+    }
+
+    #[test]
+    fn should_reject_paths_outside_of_bundle() {
+        let bundle = precompressed_bundle();
+        let nested_path = bundle.path().join("nested");
+        fs::create_dir(&nested_path).unwrap();
+
+        assert_eq!(served_encoding(&nested_path, "/../app.js", "br"), None);
+        assert_eq!(served_encoding(&nested_path, "/./../app.js", "br"), None);
+    }
+
+    #[test]
+    fn should_serve_pages_of_packed_bundle() {
+        let bundle = precompressed_bundle();
+        fs::write(bundle.path().join("breakout.html"), "breakout").unwrap();
+        let web_bundle = WebBundle::Packed(PackedBundle {
+            path: bundle.path().join("nested"),
+        });
+        fs::create_dir(bundle.path().join("nested")).unwrap();
+        fs::write(bundle.path().join("nested/index.html"), "index").unwrap();
+
+        assert_eq!(page_content(&web_bundle, "index.html").unwrap(), "index");
+        assert!(page_content(&web_bundle, "../breakout.html").is_err());
+
+        let linked_bundle = WebBundle::Linked(LinkedBundle {
+            build_artifact_path: bundle.path().to_path_buf(),
+            wasm_file_name: "app_bg.wasm".into(),
+            js_file_name: "app.js".into(),
+            assets_path: None,
+            index: Index::Static("index".to_owned()),
+        });
+        assert_eq!(page_content(&linked_bundle, "index.html").unwrap(), "index");
+        assert!(page_content(&linked_bundle, "breakout.html").is_err());
+    }
+
+    #[test]
+    fn should_inject_script_before_closing_head() {
+        let html = "<html><head><title>App</title></head><body></body></html>";
+        assert_eq!(
+            inject_script(html, "reload();\n"),
+            "<html><head><title>App</title><script>\nreload();\n</script>\n</head><body></body></html>"
+        );
+    }
+
+    #[test]
+    fn should_prepend_script_without_head() {
+        assert_eq!(
+            inject_script("<p>Hello</p>", "reload();\n"),
+            "<script>\nreload();\n</script>\n<p>Hello</p>"
+        );
+    }
+
+    #[test]
+    fn should_use_localhost_for_loopback() {
+        assert_eq!(reachable_hosts("127.0.0.1"), ["localhost"]);
+        assert_eq!(reachable_hosts("::1"), ["localhost"]);
+    }
+
+    #[test]
+    fn should_keep_specific_hosts() {
+        assert_eq!(reachable_hosts("192.168.0.2"), ["192.168.0.2"]);
+        assert_eq!(reachable_hosts("fe80::1"), ["[fe80::1]"]);
+        assert_eq!(reachable_hosts("example.local"), ["example.local"]);
+    }
+
+    #[test]
+    fn should_list_localhost_first_for_all_interfaces() {
+        assert_eq!(reachable_hosts("0.0.0.0")[0], "localhost");
+    }
+
+    #[test]
+    fn should_strip_control_characters() {
+        assert_eq!(
+            strip_control_characters("\x1b[2J\x1b]0;title\x07panicked\r\n\tat app.js\u{9b}31m"),
+            "[2J]0;titlepanicked\n\tat app.js31m"
+        );
+    }
+
+    #[test]
+    fn should_only_accept_same_origin() {
+        assert!(is_same_origin(
+            Some("http://localhost:4000"),
+            "localhost:4000"
+        ));
+        assert!(is_same_origin(
+            Some("https://192.168.0.2:4000"),
+            "192.168.0.2:4000"
+        ));
+        assert!(!is_same_origin(
+            Some("https://example.com"),
+            "localhost:4000"
+        ));
+        assert!(!is_same_origin(Some("null"), "localhost:4000"));
+        assert!(!is_same_origin(None, "localhost:4000"));
+    }
+
+    #[test]
+    fn should_format_level_without_colors() {
+        assert_eq!(format_level("warn", false), " WARN");
+        assert_eq!(format_level("table", false), "  LOG");
+    }
+}
@@ -0,0 +1,162 @@
+//! Serving the app via HTTPS.
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context as _;
+use rcgen::CertifiedKey;
+use rustls::{
+    pki_types::{pem::PemObject as _, CertificateDer, PrivateKeyDer},
+    ServerConfig,
+};
+
+use super::{args::RunWebArgs, serve::reachable_hosts};
+
+/// Create the TLS configuration to serve the app via HTTPS.
+///
+/// If no certificate has been provided by the user, a self-signed certificate is generated.
+/// It is cached in the target directory and only regenerated when the addresses of the server
+/// change.
+pub(crate) fn server_config(
+    web_args: &RunWebArgs,
+    target_directory: &Path,
+) -> anyhow::Result<ServerConfig> {
+    let (cert_path, key_path) = match (&web_args.cert, &web_args.key) {
+        (Some(cert_path), Some(key_path)) => (cert_path.clone(), key_path.clone()),
+        _ => self_signed_certificate(web_args.host(), target_directory)
+            .context("failed to create self-signed certificate")?,
+    };
+
+    let certs = CertificateDer::pem_file_iter(&cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("failed to read certificate {}", cert_path.display()))?;
+    let key = PrivateKeyDer::from_pem_file(&key_path)
+        .with_context(|| format!("failed to read private key {}", key_path.display()))?;
+
+    ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("invalid certificate or private key")
+}
+
+/// Generate a self-signed certificate and return the paths to the certificate and key files.
+///
+/// A previously generated certificate is reused if it's valid for the same names.
+fn self_signed_certificate(
+    host: &str,
+    target_directory: &Path,
+) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let directory = target_directory.join("bevy_web").join("certificate");
+    let cert_path = directory.join("cert.pem");
+    let key_path = directory.join("key.pem");
+    let names_path = directory.join("names.txt");
+
+    let names = certificate_names(host);
+    let names_list = names.join("\n");
+
+    let is_cached = cert_path.exists()
+        && key_path.exists()
+        && fs::read_to_string(&names_path).is_ok_and(|cached_names| cached_names == names_list);
+
+    if !is_cached {
+        println!("Generating a self-signed certificate...");
+
+        let CertifiedKey { cert, key_pair } = rcgen::generate_simple_self_signed(names)?;
+
+        fs::create_dir_all(&directory)?;
+        fs::write(&cert_path, cert.pem())?;
+        write_private_key(&key_path, &key_pair.serialize_pem())?;
+        fs::write(&names_path, names_list)?;
+    } else {
+        // Keys generated by previous versions of the CLI could be readable by other users
+        #[cfg(unix)]
+        fs::set_permissions(
+            &key_path,
+            std::os::unix::fs::PermissionsExt::from_mode(0o600),
+        )?;
+    }
+
+    Ok((cert_path, key_path))
+}
+
+/// Write the private key to a file which is only accessible to the current user.
+///
+/// A previous key file is removed first, so that its permissions don't carry over.
+fn write_private_key(path: &Path, key: &str) -> io::Result<()> {
+    let _ = fs::remove_file(path);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options.open(path)?.write_all(key.as_bytes())
+}
+
+/// The domain names and IP addresses the certificate should be valid for.
+fn certificate_names(host: &str) -> Vec<String> {
+    let mut names = vec![
+        "localhost".to_owned(),
+        "127.0.0.1".to_owned(),
+        "::1".to_owned(),
+    ];
+
+    for host in reachable_hosts(host) {
+        // IPv6 addresses are enclosed in brackets for use in URLs
+        let name = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_owned();
+
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_include_host_in_certificate_names() {
+        assert_eq!(
+            certificate_names("192.168.0.2"),
+            ["localhost", "127.0.0.1", "::1", "192.168.0.2"]
+        );
+        assert_eq!(
+            certificate_names("fe80::1"),
+            ["localhost", "127.0.0.1", "::1", "fe80::1"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_restrict_private_key_permissions() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let key_path = temp_dir.path().join("key.pem");
+        fs::write(&key_path, "old key").unwrap();
+
+        write_private_key(&key_path, "new key").unwrap();
+
+        assert_eq!(fs::read_to_string(&key_path).unwrap(), "new key");
+        let mode = fs::metadata(&key_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn should_not_duplicate_localhost() {
+        assert_eq!(
+            certificate_names("127.0.0.1"),
+            ["localhost", "127.0.0.1", "::1"]
+        );
+    }
+}
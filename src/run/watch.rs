@@ -0,0 +1,218 @@
+//! Watching the project files for changes.
+use std::{
+    path::{self, Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+use anyhow::Context as _;
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+/// The time to wait for further changes before reporting them.
+///
+/// Saving a file often causes multiple events in quick succession,
+/// which should only trigger a single rebuild.
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(200);
+
+/// Watches files for changes, ignoring the outputs of the build and files ignored by git.
+pub(crate) struct FileWatcher {
+    /// The underlying watcher, which stops watching when dropped.
+    _watcher: RecommendedWatcher,
+    /// Receives the events emitted by the watcher.
+    receiver: Receiver<notify::Result<Event>>,
+    /// The files and directories written by the build, which must not trigger a rebuild.
+    ignored_paths: Vec<PathBuf>,
+}
+
+impl FileWatcher {
+    /// Recursively watch the given paths for changes.
+    ///
+    /// Paths which don't exist or are already contained in a previous path are skipped.
+    /// The paths are canonicalized, so that they can be compared with the `ignored_paths`.
+    pub(crate) fn new<I, P>(paths: I, ignored_paths: &[PathBuf]) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let mut watched_paths: Vec<PathBuf> = Vec::new();
+
+        for path in paths {
+            let Ok(path) = path.as_ref().canonicalize() else {
+                continue;
+            };
+
+            if watched_paths
+                .iter()
+                .any(|watched_path| path.starts_with(watched_path))
+            {
+                continue;
+            }
+
+            watcher
+                .watch(&path, RecursiveMode::Recursive)
+                .with_context(|| format!("failed to watch {}", path.display()))?;
+            watched_paths.push(path);
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            receiver,
+            ignored_paths: ignored_paths
+                .iter()
+                .map(|path| canonicalize_or_absolute(path))
+                .collect(),
+        })
+    }
+
+    /// Block until relevant files have changed, returning their paths.
+    pub(crate) fn wait_for_change(&self) -> anyhow::Result<Vec<PathBuf>> {
+        let mut changed_paths = Vec::new();
+
+        // Wait for the first relevant change
+        while changed_paths.is_empty() {
+            let event = self.receiver.recv()??;
+            changed_paths.extend(self.relevant_paths(event));
+        }
+
+        // Collect all changes which follow shortly after
+        loop {
+            match self.receiver.recv_timeout(DEBOUNCE_DURATION) {
+                Ok(event) => changed_paths.extend(self.relevant_paths(event?)),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => anyhow::bail!("file watcher disconnected"),
+            }
+        }
+
+        changed_paths.sort();
+        changed_paths.dedup();
+        Ok(changed_paths)
+    }
+
+    /// The paths of the event which should trigger a rebuild.
+    fn relevant_paths(&self, event: Event) -> impl Iterator<Item = PathBuf> + '_ {
+        // Reading files doesn't change them
+        let is_modification = !matches!(event.kind, EventKind::Access(_));
+
+        event
+            .paths
+            .into_iter()
+            .filter(move |path| is_modification && is_relevant(path, &self.ignored_paths))
+    }
+}
+
+/// Whether changes to the given path should trigger a rebuild.
+fn is_relevant(path: &Path, ignored_paths: &[PathBuf]) -> bool {
+    if ignored_paths
+        .iter()
+        .any(|ignored_path| path.starts_with(ignored_path))
+        || path
+            .components()
+            .any(|component| component.as_os_str() == ".git")
+    {
+        return false;
+    }
+
+    !is_ignored_by_git(path)
+}
+
+/// Whether the path is ignored by git.
+///
+/// The `.gitignore` files of all parent directories up to the repository root are taken into
+/// account, the innermost ones taking precedence. The `.git/info/exclude` file of the repository
+/// applies last. The files are read on every call, so that changes to them are respected.
+fn is_ignored_by_git(path: &Path) -> bool {
+    let is_dir = path.is_dir();
+
+    for directory in path.ancestors().skip(1) {
+        let gitignore_path = directory.join(".gitignore");
+        if gitignore_path.is_file() {
+            let (gitignore, _) = Gitignore::new(gitignore_path);
+            match gitignore.matched_path_or_any_parents(path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+
+        let git_path = directory.join(".git");
+        if git_path.exists() {
+            // The exclude patterns are relative to the repository root, not to their own folder
+            let mut builder = GitignoreBuilder::new(directory);
+            builder.add(git_path.join("info").join("exclude"));
+            return builder.build().is_ok_and(|exclude| {
+                exclude
+                    .matched_path_or_any_parents(path, is_dir)
+                    .is_ignore()
+            });
+        }
+    }
+
+    false
+}
+
+/// Canonicalize the path if possible, e.g. if it exists, otherwise make it absolute.
+///
+/// Relative paths, e.g. from `--target-dir`, are relative to the current directory.
+fn canonicalize_or_absolute(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn should_respect_git_ignore_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let ignored_paths = [root.join("target"), root.join("build-cache")];
+
+        fs::create_dir_all(root.join(".git/info")).unwrap();
+        fs::write(root.join(".git/info/exclude"), "secret.txt\n").unwrap();
+        fs::write(root.join(".gitignore"), "*.log\n/generated/\n").unwrap();
+        fs::create_dir_all(root.join("crates/game/src")).unwrap();
+        fs::write(root.join("crates/game/.gitignore"), "!keep.log\nbuild/\n").unwrap();
+
+        assert!(is_relevant(
+            &root.join("crates/game/src/main.rs"),
+            &ignored_paths
+        ));
+        assert!(is_relevant(
+            &root.join("crates/game/keep.log"),
+            &ignored_paths
+        ));
+
+        assert!(!is_relevant(&root.join("target/debug/app"), &ignored_paths));
+        assert!(!is_relevant(
+            &root.join("build-cache/wasm32-unknown-unknown/web/app.wasm"),
+            &ignored_paths
+        ));
+        assert!(!is_relevant(&root.join(".git/index"), &ignored_paths));
+        assert!(!is_relevant(
+            &root.join("crates/game/debug.log"),
+            &ignored_paths
+        ));
+        assert!(!is_relevant(
+            &root.join("generated/file.rs"),
+            &ignored_paths
+        ));
+        assert!(!is_relevant(
+            &root.join("crates/game/build/out.rs"),
+            &ignored_paths
+        ));
+        assert!(!is_relevant(
+            &root.join("crates/secret.txt"),
+            &ignored_paths
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+//! Packing web bundles into zip archives, e.g. to upload them to itch.io.
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipWriter};
+
+use crate::{files, web::compression};
+
+/// Pack the contents of the bundle folder into a zip archive next to it.
+///
+/// Precompressed copies of the files are left out, because game portals serve the files
+/// themselves and only need the originals.
+///
+/// The archive is deterministic: the files are sorted by their path and all entries have the same
+/// timestamp and permissions, so the same bundle always results in the same archive.
+///
+/// Returns the path to the created archive.
+pub(crate) fn create_zip(bundle_path: &Path) -> anyhow::Result<PathBuf> {
+    let mut zip_path = bundle_path.as_os_str().to_owned();
+    zip_path.push(".zip");
+    let zip_path = PathBuf::from(zip_path);
+
+    let mut files = files::files_by_name(bundle_path)?;
+    files.retain(|_, path| !is_precompressed(path));
+
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o644);
+
+    let zip_file = File::create(&zip_path)
+        .with_context(|| format!("failed to create {}", zip_path.display()))?;
+    let mut zip = ZipWriter::new(BufWriter::new(zip_file));
+
+    for (name, path) in files {
+        zip.start_file(name, options)?;
+        let mut file =
+            File::open(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        io::copy(&mut file, &mut zip)?;
+    }
+
+    zip.finish()?;
+
+    Ok(zip_path)
+}
+
+/// Determine if the file is a precompressed copy of another file in the same folder.
+fn is_precompressed(path: &Path) -> bool {
+    compression::ENCODINGS.iter().any(|(_, extension)| {
+        path.extension().is_some_and(|ext| ext == *extension) && path.with_extension("").is_file()
+    })
+}
+
+/// Format a file size in a human-readable way, e.g. `12.3 MB`.
+pub(crate) fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1000. && unit < UNITS.len() - 1 {
+        size /= 1000.;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn should_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1_500), "1.5 KB");
+        assert_eq!(format_size(12_345_678), "12.3 MB");
+    }
+
+    /// Create a bundle folder with some files, including precompressed copies.
+    fn create_bundle(path: &Path) {
+        fs::create_dir_all(path.join("build")).unwrap();
+        fs::create_dir_all(path.join("assets/textures")).unwrap();
+        fs::write(path.join("index.html"), "<html></html>").unwrap();
+        fs::write(path.join("build/app_bg.wasm"), [0, 97, 115, 109]).unwrap();
+        fs::write(path.join("build/app_bg.wasm.br"), [1, 2, 3]).unwrap();
+        fs::write(path.join("build/app_bg.wasm.gz"), [4, 5, 6]).unwrap();
+        fs::write(path.join("assets/textures/icon.png"), [7, 8, 9]).unwrap();
+        fs::write(path.join("assets/level.gz"), [10, 11, 12]).unwrap();
+    }
+
+    #[test]
+    fn should_create_reproducible_archive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bundle_path = temp_dir.path().join("app");
+        create_bundle(&bundle_path);
+
+        let zip_path = create_zip(&bundle_path).unwrap();
+        let first = fs::read(&zip_path).unwrap();
+
+        // Recreating the folder changes the file timestamps and possibly the directory order
+        fs::remove_dir_all(&bundle_path).unwrap();
+        create_bundle(&bundle_path);
+
+        let zip_path = create_zip(&bundle_path).unwrap();
+        let second = fs::read(&zip_path).unwrap();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn should_skip_precompressed_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bundle_path = temp_dir.path().join("app");
+        create_bundle(&bundle_path);
+
+        let zip_path = create_zip(&bundle_path).unwrap();
+        let archive = zip::ZipArchive::new(File::open(zip_path).unwrap()).unwrap();
+        let names: Vec<_> = archive.file_names().collect();
+
+        assert_eq!(
+            names,
+            [
+                "assets/level.gz",
+                "assets/textures/icon.png",
+                "build/app_bg.wasm",
+                "index.html"
+            ]
+        );
+    }
+}
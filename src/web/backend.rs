@@ -0,0 +1,75 @@
+//! Selecting the rendering backend of web builds.
+//!
+//! Bevy renders with WebGL2 or WebGPU in the browser, selected with the mutually exclusive
+//! `webgl2` and `webgpu` features. Not all browsers support WebGPU yet, so an app can also be
+//! built for both backends, letting the `index.html` pick the best one at runtime.
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// The suffix of the WebGPU build, if the app is built for both backends.
+const WEBGPU_SUFFIX: &str = "_webgpu";
+
+/// The rendering backend to build the app for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Render with WebGPU, which is faster but not supported by all browsers.
+    Webgpu,
+    /// Render with WebGL2, which is supported by all modern browsers.
+    Webgl2,
+    /// Build for both backends, using WebGPU if the browser supports it.
+    Both,
+}
+
+/// A single Wasm binary built for one rendering backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BackendBuild {
+    /// The Cargo feature selecting the backend.
+    pub feature: &'static str,
+    /// Appended to the name of the output files, to distinguish them from the other build.
+    pub suffix: &'static str,
+}
+
+impl Backend {
+    /// The binaries to build for this backend.
+    ///
+    /// The WebGL2 build comes first, as it is the fallback which is loaded by default.
+    pub(crate) fn builds(self) -> Vec<BackendBuild> {
+        let webgl2 = BackendBuild {
+            feature: "webgl2",
+            suffix: "",
+        };
+        let webgpu = BackendBuild {
+            feature: "webgpu",
+            suffix: "",
+        };
+
+        match self {
+            Self::Webgl2 => vec![webgl2],
+            Self::Webgpu => vec![webgpu],
+            Self::Both => vec![
+                webgl2,
+                BackendBuild {
+                    suffix: WEBGPU_SUFFIX,
+                    ..webgpu
+                },
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_build_webgl2_fallback_first() {
+        let builds = Backend::Both.builds();
+        assert_eq!(builds[0].feature, "webgl2");
+        assert_eq!(builds[0].suffix, "");
+        assert_eq!(builds[1].feature, "webgpu");
+        assert_eq!(builds[1].suffix, "_webgpu");
+
+        assert_eq!(Backend::Webgpu.builds()[0].suffix, "");
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     ffi::OsString,
     fs,
     path::{Path, PathBuf},
@@ -6,14 +7,37 @@ use std::{
 
 use anyhow::Context;
 
-use crate::{external_cli::cargo::metadata::Metadata, run::BinTarget};
+use crate::{
+    external_cli::{
+        cargo::metadata::Metadata,
+        wasm_bindgen::{BindgenOptions, BindgenTarget},
+    },
+    files,
+    run::BinTarget,
+    web::{
+        compression,
+        folders::PackageFolders,
+        hashing, index,
+        pwa::{self, PwaOptions},
+        threads,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub enum Index {
-    /// The folder containing a custom `index.html` file.
-    Folder(PathBuf),
-    /// A static string representing the contents of `index.html`.
-    Static(&'static str),
+    /// The folder containing a custom `index.html` file, together with its rendered contents.
+    Folder { path: PathBuf, contents: String },
+    /// A string representing the contents of `index.html`.
+    Static(String),
+}
+
+impl Index {
+    /// The rendered contents of `index.html`.
+    pub fn contents(&self) -> &str {
+        match self {
+            Self::Folder { contents, .. } | Self::Static(contents) => contents,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -36,6 +60,33 @@ pub struct PackedBundle {
     pub path: PathBuf,
 }
 
+/// Options for bundles which are packed into a single folder.
+#[derive(Debug, Clone, Default)]
+pub struct PackOptions {
+    /// Include a `_headers` file with the headers required for Wasm threads,
+    /// unless the custom web folder already contains one.
+    pub multithreaded: bool,
+    /// Add content hashes to the file names of the build artifacts.
+    pub hash_file_names: bool,
+    /// Add copies of the assets with content hashes in their file names.
+    pub hash_assets: bool,
+    /// Create gzip and Brotli compressed copies of the files.
+    pub precompress: bool,
+    /// Turn the bundle into a progressive web app, which can be installed and works offline.
+    pub pwa: Option<PwaOptions>,
+}
+
+/// The template of the default `index.html`.
+pub(crate) const DEFAULT_INDEX: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/assets/web/index.html"
+));
+
+/// The directory containing the packed bundles of the given profile, `target/bevy_web/{profile}`.
+pub(crate) fn bundles_directory(metadata: &Metadata, profile: &str) -> PathBuf {
+    metadata.target_directory.join("bevy_web").join(profile)
+}
+
 /// A bundle of all the files needed to serve the app in the web.
 #[derive(Debug, Clone)]
 pub enum WebBundle {
@@ -48,24 +99,57 @@ pub enum WebBundle {
 
 /// Create a bundle of all the files needed for serving the app in the web.
 ///
-/// If `packed` is set to `true`, the files will be packed together in a single folder.
+/// If `packed` options are given, the files will be packed together in a single folder.
 /// Use this option e.g. to upload it to a web server.
 ///
 /// Otherwise, the assets and build artifacts will be kept at their original place
-/// to avoid duplication.
-pub fn create_web_bundle(
-    metadata: &Metadata,
-    profile: &str,
+/// to avoid duplication. Packed bundles are placed in `bundles_directory`, see
+/// [`bundles_directory`].
+///
+/// In both cases, the placeholders in `index.html` are replaced, see [`index`].
+///
+/// If the app is built for both rendering backends, `webgpu_out_name` is the name of the
+/// bindings of the WebGPU build, which are bundled in addition to the default ones.
+pub(crate) fn create_web_bundle(
+    bundles_directory: &Path,
     bin_target: &BinTarget,
-    packed: bool,
+    folders: &PackageFolders,
+    template_values: &BTreeMap<String, String>,
+    bindgen_options: &BindgenOptions,
+    webgpu_out_name: Option<&str>,
+    packed: Option<PackOptions>,
 ) -> anyhow::Result<WebBundle> {
-    let assets_path = Path::new("assets");
+    let assets_path = &folders.assets;
     // The "_bg" suffix is needed to reference the bindings created by wasm_bindgen,
     // instead of the artifact created directly by cargo.
     let wasm_file_name = OsString::from(format!("{}_bg.wasm", bin_target.bin_name));
     let js_file_name = OsString::from(format!("{}.js", bin_target.bin_name));
 
-    let custom_web_folder = Path::new("web");
+    let custom_web_folder = &folders.web;
+    let custom_index_path = custom_web_folder.join("index.html");
+
+    let wasm_size = fs::metadata(bin_target.artifact_directory.join(&wasm_file_name))
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+    let template_values = index::template_values(
+        bin_target,
+        wasm_size,
+        bindgen_options.target,
+        webgpu_out_name,
+        template_values,
+    );
+
+    let index = if custom_index_path.exists() {
+        let template = fs::read_to_string(&custom_index_path)
+            .with_context(|| format!("failed to read {}", custom_index_path.display()))?;
+        Index::Folder {
+            path: custom_web_folder.to_path_buf(),
+            contents: index::render_html(&template, &template_values),
+        }
+    } else {
+        println!("No custom `web` folder found, using defaults.");
+        Index::Static(index::render_html(DEFAULT_INDEX, &template_values))
+    };
 
     let linked = LinkedBundle {
         build_artifact_path: bin_target.artifact_directory.clone(),
@@ -76,23 +160,14 @@ pub fn create_web_bundle(
         } else {
             None
         },
-        index: if custom_web_folder.join("index.html").exists() {
-            Index::Folder(custom_web_folder.to_path_buf())
-        } else {
-            println!("No custom `web` folder found, using defaults.");
-            Index::Static(default_index(bin_target))
-        },
+        index,
     };
 
-    if !packed {
+    let Some(options) = packed else {
         return Ok(WebBundle::Linked(linked));
-    }
+    };
 
-    let base_path = metadata
-        .target_directory
-        .join("bevy_web")
-        .join(profile)
-        .join(&bin_target.bin_name);
+    let base_path = bundles_directory.join(&bin_target.bin_name);
 
     // Remove the previous bundle
     // The error can be ignored, because the folder doesn't need to exist yet
@@ -101,70 +176,96 @@ pub fn create_web_bundle(
 
     // Build artifacts
     fs::create_dir_all(base_path.join("build"))?;
-    fs::copy(
-        linked.build_artifact_path.join(&linked.wasm_file_name),
-        base_path.join("build").join(&linked.wasm_file_name),
-    )
-    .context("failed to copy WASM artifact")?;
-    fs::copy(
-        linked.build_artifact_path.join(&linked.js_file_name),
-        base_path.join("build").join(&linked.js_file_name),
-    )
-    .context("failed to copy JS artifact")?;
-
-    // Assets
-    if let Some(assets_path) = linked.assets_path {
+    let out_names: Vec<&str> = [bin_target.bin_name.as_str()]
+        .into_iter()
+        .chain(webgpu_out_name)
+        .collect();
+    let file_names = out_names
+        .iter()
+        .flat_map(|out_name| bindgen_options.output_file_names(out_name));
+    for file_name in file_names {
+        fs::copy(
+            linked.build_artifact_path.join(&file_name),
+            base_path.join("build").join(&file_name),
+        )
+        .with_context(|| format!("failed to copy build artifact {file_name}"))?;
+    }
+
+    copy_snippets(&linked.build_artifact_path, &base_path)?;
+    files::copy_assets(assets_path, &base_path)?;
+
+    // Index
+    let index_path = base_path.join("index.html");
+    if let Index::Folder { path, .. } = &linked.index {
+        copy_custom_web_folder(path, &base_path)?;
+    }
+    // With the `bundler` target, the bindings are loaded by the website embedding the app instead
+    if bindgen_options.target != BindgenTarget::Bundler
+        || matches!(linked.index, Index::Folder { .. })
+    {
+        // The copied template is replaced by the rendered version
+        fs::write(index_path, linked.index.contents()).context("failed to create index.html")?;
+    }
+
+    finish_packed_bundle(&base_path, &out_names, &template_values["title"], &options)?;
+
+    Ok(WebBundle::Packed(PackedBundle { path: base_path }))
+}
+
+/// Copy the JS modules linked by the app, which wasm-bindgen creates in the `snippets` folder, into
+/// the `build` folder of the bundle.
+pub(crate) fn copy_snippets(artifact_directory: &Path, base_path: &Path) -> anyhow::Result<()> {
+    let snippets_path = artifact_directory.join("snippets");
+    if snippets_path.exists() {
         fs_extra::dir::copy(
-            assets_path,
-            &base_path,
+            snippets_path,
+            base_path.join("build"),
             &fs_extra::dir::CopyOptions {
                 overwrite: true,
                 ..Default::default()
             },
         )
-        .context("failed to copy assets")?;
+        .context("failed to copy JS snippets")?;
     }
 
-    // Index
-    let index_path = base_path.join("index.html");
-    match linked.index {
-        Index::Folder(path) => {
-            fs_extra::dir::copy(
-                path,
-                &base_path,
-                &fs_extra::dir::CopyOptions {
-                    overwrite: true,
-                    content_only: true,
-                    ..Default::default()
-                },
-            )
-            .context("failed to copy custom web assets")?;
-        }
-        Index::Static(contents) => {
-            fs::write(index_path, contents).context("failed to create index.html")?;
-        }
-    }
+    Ok(())
+}
 
-    Ok(WebBundle::Packed(PackedBundle { path: base_path }))
+/// Copy the files of the custom web folder into the bundle.
+pub(crate) fn copy_custom_web_folder(web_path: &Path, base_path: &Path) -> anyhow::Result<()> {
+    files::copy_dir_contents(web_path, base_path).context("failed to copy custom web assets")
 }
 
-/// Returns the contents of the default `index.html`,
-/// customized to use the name of the generated binary.
-fn default_index(bin_target: &BinTarget) -> &'static str {
-    let template = include_str!(concat!(
-        env!("CARGO_MANIFEST_DIR"),
-        "/assets/web/index.html"
-    ));
-
-    // Insert correct path to JS bindings
-    let index = template.replace(
-        "./build/bevy_app.js",
-        format!("./build/{}.js", bin_target.bin_name).as_str(),
-    );
+/// Apply the [`PackOptions`] which process the complete bundle, after all files are copied.
+///
+/// `out_names` are the names of the bindings whose references are updated when hashing the file
+/// names, `title` is the name of the progressive web app.
+pub(crate) fn finish_packed_bundle(
+    base_path: &Path,
+    out_names: &[&str],
+    title: &str,
+    options: &PackOptions,
+) -> anyhow::Result<()> {
+    // Headers for static hosting providers
+    let headers_path = base_path.join("_headers");
+    if options.multithreaded && !headers_path.exists() {
+        fs::write(headers_path, threads::headers_file()).context("failed to create _headers")?;
+    }
+
+    // The compressed files need to have the hashed file names as well
+    if options.hash_file_names {
+        hashing::hash_file_names(base_path, out_names, options.hash_assets)
+            .context("failed to add content hashes to file names")?;
+    }
+
+    if let Some(pwa_options) = &options.pwa {
+        pwa::create_pwa(base_path, title, pwa_options)
+            .context("failed to create progressive web app")?;
+    }
+
+    if options.precompress {
+        compression::precompress_folder(base_path).context("failed to precompress files")?;
+    }
 
-    // Only static strings can be served in the web app,
-    // so we leak the string memory to convert it to a static reference.
-    // PERF: This is assumed to be used only once and is needed for the rest of the app running
-    // time, making the memory leak acceptable.
-    Box::leak(index.into_boxed_str())
+    Ok(())
 }
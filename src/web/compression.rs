@@ -0,0 +1,174 @@
+//! Precompressing the files of packed web bundles.
+//!
+//! Web servers and hosting providers can serve the precompressed `.br` and `.gz` siblings of a file
+//! directly, instead of compressing it on every request.
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::Context as _;
+use flate2::{write::GzEncoder, Compression};
+
+/// The supported encodings, with the value of the `Content-Encoding` header and the file
+/// extension of the precompressed files, in order of preference.
+pub(crate) const ENCODINGS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+/// The file extensions of formats which benefit from compression.
+///
+/// Most image and audio formats are already compressed, so they are not included.
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &[
+    "wasm", "js", "mjs", "html", "css", "json", "svg", "txt", "xml", "wgsl", "glsl", "gltf", "ron",
+    "obj", "ttf", "otf",
+];
+
+/// Files smaller than this are not worth compressing.
+const MIN_SIZE: u64 = 1024;
+
+/// The Brotli quality level, from 0 to 11.
+const BROTLI_QUALITY: u32 = 11;
+
+/// The base-2 logarithm of the Brotli window size, from 10 to 24.
+const BROTLI_WINDOW_SIZE: u32 = 24;
+
+/// Create precompressed copies of the compressible files in the folder.
+///
+/// The compressed files are placed next to the originals, with an additional extension for the
+/// encoding, e.g. `app_bg.wasm.br`. They are only kept if they are smaller than the original.
+pub(crate) fn precompress_folder(path: &Path) -> anyhow::Result<()> {
+    println!("Precompressing files...");
+
+    let start = Instant::now();
+    let mut size_before = 0;
+    let mut size_after = 0;
+
+    for file_path in compressible_files(path)? {
+        let content = fs::read(&file_path)
+            .with_context(|| format!("failed to read {}", file_path.display()))?;
+
+        let compressed_files = [
+            ("br", compress_brotli(&content)?),
+            ("gz", compress_gzip(&content)?),
+        ];
+        let mut smallest_size = content.len();
+
+        for (extension, compressed) in compressed_files {
+            if compressed.len() >= content.len() {
+                continue;
+            }
+
+            let compressed_path = append_extension(&file_path, extension);
+            fs::write(&compressed_path, &compressed)
+                .with_context(|| format!("failed to write {}", compressed_path.display()))?;
+            smallest_size = smallest_size.min(compressed.len());
+        }
+
+        size_before += content.len();
+        size_after += smallest_size;
+    }
+
+    let size_reduction = 1. - (size_after as f32) / (size_before.max(1) as f32);
+    println!(
+        "Precompressed files in {:.2?}, reducing the transferred size by {:.0}%.",
+        start.elapsed(),
+        size_reduction * 100.
+    );
+
+    Ok(())
+}
+
+/// The path of the precompressed file with the given extension, e.g. `app.js.gz`.
+pub(crate) fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    path.into()
+}
+
+/// Recursively find all files in the folder which are worth compressing.
+fn compressible_files(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            files.extend(compressible_files(&entry.path())?);
+        } else if file_type.is_file()
+            && entry.metadata()?.len() >= MIN_SIZE
+            && is_compressible(&entry.path())
+        {
+            files.push(entry.path());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Determine if the format of the file benefits from compression.
+fn is_compressible(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            COMPRESSIBLE_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+        })
+}
+
+fn compress_brotli(content: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut encoder =
+        brotli::CompressorWriter::new(Vec::new(), 4096, BROTLI_QUALITY, BROTLI_WINDOW_SIZE);
+    encoder.write_all(content)?;
+    encoder.flush()?;
+    Ok(encoder.into_inner())
+}
+
+fn compress_gzip(content: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(content)?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    #[test]
+    fn should_append_extension() {
+        assert_eq!(
+            append_extension(Path::new("build/app_bg.wasm"), "br"),
+            Path::new("build/app_bg.wasm.br")
+        );
+    }
+
+    #[test]
+    fn should_only_compress_suitable_formats() {
+        assert!(is_compressible(Path::new("build/app_bg.wasm")));
+        assert!(is_compressible(Path::new("assets/shaders/custom.WGSL")));
+        assert!(!is_compressible(Path::new("assets/icon.png")));
+        assert!(!is_compressible(Path::new("LICENSE")));
+    }
+
+    #[test]
+    fn should_roundtrip_compressed_content() {
+        let content = "Hello, Bevy!".repeat(100).into_bytes();
+
+        let mut decompressed = Vec::new();
+        GzDecoder::new(compress_gzip(&content).unwrap().as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, content);
+
+        let mut decompressed = Vec::new();
+        brotli::Decompressor::new(compress_brotli(&content).unwrap().as_slice(), 4096)
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, content);
+    }
+}
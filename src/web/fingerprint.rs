@@ -0,0 +1,141 @@
+//! Skipping the post-processing of Wasm binaries which did not change.
+//!
+//! Running `wasm-bindgen` and especially `wasm-opt` takes a while, even if `cargo build` had
+//! nothing to do. The fingerprint of the compiled Wasm binary and the options of the
+//! post-processing is stored next to the outputs, so that unchanged builds can reuse them.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use sha2::{Digest as _, Sha256};
+
+use crate::run::BinTarget;
+
+/// The fingerprint of the inputs of `wasm-bindgen` and `wasm-opt`.
+#[derive(Debug, Clone)]
+pub(crate) struct Fingerprint {
+    /// The file storing the fingerprint of the last post-processing.
+    path: PathBuf,
+    /// The outputs which need to exist to be reused.
+    outputs: Vec<PathBuf>,
+    /// The hash of the inputs.
+    hash: String,
+}
+
+impl Fingerprint {
+    /// Compute the fingerprint of the compiled Wasm binary and the given options, for the
+    /// bindings named `out_name`.
+    ///
+    /// The options are all values which influence the post-processing, like whether `wasm-opt`
+    /// is run.
+    pub(crate) fn new(
+        bin_target: &BinTarget,
+        out_name: &str,
+        options: &[(&str, String)],
+    ) -> anyhow::Result<Self> {
+        let directory = &bin_target.artifact_directory;
+        let input_path = directory.join(format!("{}.wasm", bin_target.bin_name));
+
+        let mut hasher = Sha256::new();
+        // A new version of the CLI might post-process the binary differently
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        for (name, value) in options {
+            hasher.update(format!("\n{name}={value}"));
+        }
+        hasher.update("\n");
+        hasher.update(
+            fs::read(&input_path)
+                .with_context(|| format!("failed to read {}", input_path.display()))?,
+        );
+
+        Ok(Self {
+            path: directory.join(format!("{out_name}.bevy-fingerprint")),
+            outputs: vec![
+                directory.join(format!("{out_name}.js")),
+                directory.join(format!("{out_name}_bg.wasm")),
+            ],
+            hash: format!("{:x}", hasher.finalize()),
+        })
+    }
+
+    /// Whether the outputs of the last post-processing match this fingerprint.
+    pub(crate) fn is_up_to_date(&self) -> bool {
+        fs::read_to_string(&self.path).is_ok_and(|hash| hash == self.hash)
+            && self.outputs.iter().all(|output| output.exists())
+    }
+
+    /// Forget the stored fingerprint, before the outputs are overwritten.
+    ///
+    /// This makes sure that outputs of interrupted or failed runs are not reused.
+    pub(crate) fn invalidate(&self) -> anyhow::Result<()> {
+        remove_if_exists(&self.path)
+    }
+
+    /// Store the fingerprint, after the outputs have been created successfully.
+    pub(crate) fn save(&self) -> anyhow::Result<()> {
+        fs::write(&self.path, &self.hash)
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+}
+
+fn remove_if_exists(path: &Path) -> anyhow::Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+            Err(error).with_context(|| format!("failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_changes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let bin_target = BinTarget {
+            artifact_directory: temp_dir.path().to_path_buf(),
+            bin_name: "my_game".to_owned(),
+            package_name: "my-game".to_owned(),
+            package_version: "0.1.0".to_owned(),
+        };
+        let options = [("optimize", "true".to_owned())];
+        fs::write(temp_dir.path().join("my_game.wasm"), "wasm").unwrap();
+
+        let fingerprint = Fingerprint::new(&bin_target, "my_game", &options).unwrap();
+        assert!(!fingerprint.is_up_to_date());
+
+        fs::write(temp_dir.path().join("my_game.js"), "js").unwrap();
+        fs::write(temp_dir.path().join("my_game_bg.wasm"), "wasm").unwrap();
+        fingerprint.save().unwrap();
+        assert!(Fingerprint::new(&bin_target, "my_game", &options)
+            .unwrap()
+            .is_up_to_date());
+
+        // Different options
+        assert!(
+            !Fingerprint::new(&bin_target, "my_game", &[("optimize", "false".to_owned())])
+                .unwrap()
+                .is_up_to_date()
+        );
+
+        // Different binary
+        fs::write(temp_dir.path().join("my_game.wasm"), "changed").unwrap();
+        assert!(!Fingerprint::new(&bin_target, "my_game", &options)
+            .unwrap()
+            .is_up_to_date());
+
+        // Missing output
+        fs::write(temp_dir.path().join("my_game.wasm"), "wasm").unwrap();
+        fs::remove_file(temp_dir.path().join("my_game.js")).unwrap();
+        assert!(!Fingerprint::new(&bin_target, "my_game", &options)
+            .unwrap()
+            .is_up_to_date());
+
+        fingerprint.invalidate().unwrap();
+        fingerprint.invalidate().unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+//! Locating the assets and web folders of a package.
+//!
+//! Bevy loads assets from the `assets` folder next to the manifest of the package, unless the
+//! `BEVY_ASSET_ROOT` environment variable points to another directory. The folders are resolved
+//! the same way, so that the app works regardless of the directory the CLI is run in.
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// The environment variable which Bevy uses instead of the package directory to find the assets.
+const ASSET_ROOT_VAR: &str = "BEVY_ASSET_ROOT";
+
+/// The folders of a package which are bundled with the app.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PackageFolders {
+    /// The Bevy assets folder, which might not exist.
+    pub assets: PathBuf,
+    /// The folder with a custom `index.html` and other web files, which might not exist.
+    pub web: PathBuf,
+}
+
+impl PackageFolders {
+    /// Resolve the folders of the package in `package_dir`.
+    ///
+    /// Like the `file_path` of Bevy's `AssetPlugin`, a custom `assets_path` is relative to
+    /// `BEVY_ASSET_ROOT` or the package directory. A custom `web_path` is relative to the package
+    /// directory.
+    pub(crate) fn resolve(
+        package_dir: &Path,
+        assets_path: Option<&Path>,
+        web_path: Option<&Path>,
+    ) -> Self {
+        Self::resolve_with_asset_root(
+            package_dir,
+            env::var_os(ASSET_ROOT_VAR).map(PathBuf::from).as_deref(),
+            assets_path,
+            web_path,
+        )
+    }
+
+    fn resolve_with_asset_root(
+        package_dir: &Path,
+        asset_root: Option<&Path>,
+        assets_path: Option<&Path>,
+        web_path: Option<&Path>,
+    ) -> Self {
+        Self {
+            assets: asset_root
+                .unwrap_or(package_dir)
+                .join(assets_path.unwrap_or(Path::new("assets"))),
+            web: package_dir.join(web_path.unwrap_or(Path::new("web"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_resolve_relative_to_package() {
+        let package_dir = Path::new("/workspace/crates/game");
+
+        assert_eq!(
+            PackageFolders::resolve_with_asset_root(package_dir, None, None, None),
+            PackageFolders {
+                assets: PathBuf::from("/workspace/crates/game/assets"),
+                web: PathBuf::from("/workspace/crates/game/web"),
+            }
+        );
+        assert_eq!(
+            PackageFolders::resolve_with_asset_root(
+                package_dir,
+                None,
+                Some(Path::new("../../assets")),
+                Some(Path::new("static")),
+            ),
+            PackageFolders {
+                assets: PathBuf::from("/workspace/crates/game/../../assets"),
+                web: PathBuf::from("/workspace/crates/game/static"),
+            }
+        );
+    }
+
+    #[test]
+    fn should_prefer_asset_root() {
+        let folders = PackageFolders::resolve_with_asset_root(
+            Path::new("/workspace/crates/game"),
+            Some(Path::new("/workspace")),
+            Some(Path::new("game_assets")),
+            None,
+        );
+
+        assert_eq!(folders.assets, PathBuf::from("/workspace/game_assets"));
+        assert_eq!(folders.web, PathBuf::from("/workspace/crates/game/web"));
+    }
+}
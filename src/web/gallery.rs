@@ -0,0 +1,199 @@
+//! Bundling all examples of a package into a single gallery.
+//!
+//! Every example gets its own page next to a generated `index.html` linking to all of them.
+//! The examples share the build folder, the assets and the files of the custom `web` folder,
+//! so that the whole gallery can be served by one web server or deployed as a single folder.
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{ensure, Context as _};
+
+use crate::{
+    external_cli::{cargo::metadata::Package, wasm_bindgen::BindgenOptions},
+    files,
+    run::BinTarget,
+    web::{
+        bundle::{self, PackOptions, PackedBundle, WebBundle, DEFAULT_INDEX},
+        folders::PackageFolders,
+        index,
+    },
+};
+
+/// The template of the `index.html` linking to the examples.
+const GALLERY_TEMPLATE: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/assets/web/gallery.html"
+));
+
+/// Pack the bindings of the examples into a gallery at `{package}-examples` in the
+/// `bundles_directory`, see [`bundles_directory`](super::bundle::bundles_directory).
+///
+/// The page of each example is rendered from the custom or default `index.html` and named after
+/// the example, e.g. `breakout.html`.
+pub(crate) fn create_gallery(
+    bundles_directory: &Path,
+    package: &Package,
+    folders: &PackageFolders,
+    examples: &[BinTarget],
+    template_values: &BTreeMap<String, String>,
+    bindgen_options: &BindgenOptions,
+    options: &PackOptions,
+) -> anyhow::Result<WebBundle> {
+    ensure!(
+        examples.iter().all(|example| example.bin_name != "index"),
+        "an example named `index` would replace the gallery page"
+    );
+
+    let custom_web_folder = &folders.web;
+    let custom_index_path = custom_web_folder.join("index.html");
+
+    let template = if custom_index_path.exists() {
+        fs::read_to_string(&custom_index_path)
+            .with_context(|| format!("failed to read {}", custom_index_path.display()))?
+    } else {
+        println!("No custom `web` folder found, using defaults.");
+        DEFAULT_INDEX.to_owned()
+    };
+
+    let base_path = bundles_directory.join(format!("{}-examples", package.name));
+
+    // Remove the previous bundle
+    // The error can be ignored, because the folder doesn't need to exist yet
+    let _ = fs::remove_dir_all(&base_path);
+    fs::create_dir_all(base_path.join("build"))?;
+
+    // Assets and the files of the custom web folder are shared by all examples
+    files::copy_assets(&folders.assets, &base_path)?;
+    if custom_web_folder.exists() {
+        bundle::copy_custom_web_folder(custom_web_folder, &base_path)?;
+    }
+
+    for example in examples {
+        for file_name in bindgen_options.output_file_names(&example.bin_name) {
+            fs::copy(
+                example.artifact_directory.join(&file_name),
+                base_path.join("build").join(&file_name),
+            )
+            .with_context(|| format!("failed to copy build artifact {file_name}"))?;
+        }
+
+        let page_name = format!("{}.html", example.bin_name);
+        fs::write(
+            base_path.join(&page_name),
+            example_page(&template, example, template_values, bindgen_options),
+        )
+        .with_context(|| format!("failed to create {page_name}"))?;
+    }
+
+    // The examples share the artifact directory and thereby the snippets
+    if let Some(example) = examples.first() {
+        bundle::copy_snippets(&example.artifact_directory, &base_path)?;
+    }
+
+    let title = template_values
+        .get("title")
+        .map_or_else(|| format!("{} examples", package.name), Clone::clone);
+    fs::write(
+        base_path.join("index.html"),
+        gallery_index(&title, examples),
+    )
+    .context("failed to create index.html")?;
+
+    bundle::finish_packed_bundle(&base_path, &[], &title, options)?;
+
+    Ok(WebBundle::Packed(PackedBundle { path: base_path }))
+}
+
+/// Render the page of a single example.
+///
+/// The title defaults to the name of the example, unless it is configured.
+fn example_page(
+    template: &str,
+    example: &BinTarget,
+    custom_values: &BTreeMap<String, String>,
+    bindgen_options: &BindgenOptions,
+) -> String {
+    let wasm_size = fs::metadata(
+        example
+            .artifact_directory
+            .join(format!("{}_bg.wasm", example.bin_name)),
+    )
+    .map(|metadata| metadata.len())
+    .unwrap_or_default();
+
+    let mut values = index::template_values(
+        example,
+        wasm_size,
+        bindgen_options.target,
+        None,
+        custom_values,
+    );
+    if !custom_values.contains_key("title") {
+        values.insert("title".to_owned(), example.bin_name.clone());
+    }
+
+    index::render_html(template, &values)
+}
+
+/// Render the `index.html` linking to the pages of the examples.
+fn gallery_index(title: &str, examples: &[BinTarget]) -> String {
+    let links = examples
+        .iter()
+        .map(|example| {
+            format!(
+                "      <li><a href=\"./{0}.html\">{0}</a></li>",
+                index::escape_html(&example.bin_name)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // The links are already HTML
+    let values = BTreeMap::from([
+        ("title".to_owned(), index::escape_html(title)),
+        ("examples".to_owned(), links),
+    ]);
+    index::render(GALLERY_TEMPLATE, &values)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn example(name: &str) -> BinTarget {
+        BinTarget {
+            artifact_directory: PathBuf::from("target/wasm32-unknown-unknown/web/examples"),
+            bin_name: name.to_owned(),
+            package_name: "my-game".to_owned(),
+            package_version: "0.1.0".to_owned(),
+        }
+    }
+
+    #[test]
+    fn should_link_to_all_examples() {
+        let index = gallery_index("My Game", &[example("breakout"), example("sprite")]);
+
+        assert!(index.contains("<title>My Game</title>"));
+        assert!(index.contains("<a href=\"./breakout.html\">breakout</a>"));
+        assert!(index.contains("<a href=\"./sprite.html\">sprite</a>"));
+        assert!(!index.contains("{{"));
+    }
+
+    #[test]
+    fn should_render_example_page() {
+        let template = "<title>{{ title }}</title><script src=\"{{ js_path }}\"></script>";
+        let options = BindgenOptions::default();
+
+        assert_eq!(
+            example_page(template, &example("breakout"), &BTreeMap::new(), &options),
+            "<title>breakout</title><script src=\"./build/breakout.js\"></script>"
+        );
+
+        let custom_values = BTreeMap::from([("title".to_owned(), "My Game".to_owned())]);
+        assert!(
+            example_page(template, &example("breakout"), &custom_values, &options)
+                .starts_with("<title>My Game</title>")
+        );
+    }
+}
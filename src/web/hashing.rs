@@ -0,0 +1,231 @@
+//! Adding content hashes to the file names of packed web bundles.
+//!
+//! A changed file gets a new name, so browsers never use stale files from their cache and the
+//! files can be cached indefinitely.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use sha2::{Digest as _, Sha256};
+
+use crate::files;
+
+/// The name of the manifest mapping the original file paths to the hashed ones.
+const MANIFEST_FILE_NAME: &str = "asset-manifest.json";
+
+/// The number of hex digits of the hash that are included in the file names.
+const HASH_LENGTH: usize = 16;
+
+/// Rename the build artifacts (and optionally the assets) in the packed bundle to include a hash
+/// of their content.
+///
+/// The references to the build artifacts in `index.html` and the JS bindings are updated
+/// accordingly. The paths of all renamed files are written to a manifest in the bundle.
+///
+/// Bevy loads assets by their original paths, so the assets are copied to their hashed paths
+/// instead of being renamed. This way, the app keeps working, while the hashed copies can be used
+/// via the manifest.
+///
+/// `out_names` are the names of the bindings created by wasm-bindgen, e.g. `app` for `app.js` and
+/// `app_bg.wasm`.
+pub(crate) fn hash_file_names(
+    base_path: &Path,
+    out_names: &[&str],
+    hash_assets: bool,
+) -> anyhow::Result<()> {
+    let mut manifest = BTreeMap::new();
+    let build_path = base_path.join("build");
+
+    for out_name in out_names {
+        let wasm_file_name = format!("{out_name}_bg.wasm");
+        let js_file_name = format!("{out_name}.js");
+
+        // The JS bindings reference the Wasm binary, so it needs to be renamed first
+        let hashed_wasm_file_name = hash_file(&build_path.join(&wasm_file_name), false)?;
+        replace_reference(
+            &build_path.join(&js_file_name),
+            &format!("'{wasm_file_name}'"),
+            &format!("'{hashed_wasm_file_name}'"),
+        )?;
+
+        let hashed_js_file_name = hash_file(&build_path.join(&js_file_name), false)?;
+        replace_reference(
+            &base_path.join("index.html"),
+            &format!("build/{js_file_name}"),
+            &format!("build/{hashed_js_file_name}"),
+        )?;
+
+        manifest.insert(
+            format!("build/{wasm_file_name}"),
+            format!("build/{hashed_wasm_file_name}"),
+        );
+        manifest.insert(
+            format!("build/{js_file_name}"),
+            format!("build/{hashed_js_file_name}"),
+        );
+    }
+
+    let assets_path = base_path.join("assets");
+    if hash_assets && assets_path.exists() {
+        // Meta files are copied along with their asset
+        let asset_files = files::files_by_name(&assets_path)?
+            .into_values()
+            .filter(|path| path.extension().is_none_or(|extension| extension != "meta"));
+        for path in asset_files {
+            let hashed_file_name = hash_file(&path, true)?;
+            let hashed_path = path.with_file_name(&hashed_file_name);
+
+            // Bevy looks for the meta file next to the asset, so it has to be copied as well
+            let meta_path = append_meta_extension(&path);
+            if meta_path.exists() {
+                fs::copy(&meta_path, append_meta_extension(&hashed_path))?;
+            }
+
+            manifest.insert(
+                files::relative_name(base_path, &path),
+                files::relative_name(base_path, &hashed_path),
+            );
+        }
+    }
+
+    fs::write(
+        base_path.join(MANIFEST_FILE_NAME),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .with_context(|| format!("failed to create {MANIFEST_FILE_NAME}"))?;
+
+    Ok(())
+}
+
+/// Rename the file to include a hash of its content, returning the new file name.
+///
+/// If `keep_original` is set, the file is copied instead.
+fn hash_file(path: &Path, keep_original: bool) -> anyhow::Result<String> {
+    let content = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let file_name = path
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .with_context(|| format!("invalid file name {}", path.display()))?;
+
+    let hashed_file_name = hashed_file_name(file_name, &content);
+    let hashed_path = path.with_file_name(&hashed_file_name);
+    if keep_original {
+        fs::copy(path, hashed_path).map(|_| ())
+    } else {
+        fs::rename(path, hashed_path)
+    }
+    .with_context(|| format!("failed to rename {}", path.display()))?;
+
+    Ok(hashed_file_name)
+}
+
+/// Insert the hash of the content before the extension of the file name,
+/// e.g. `app_bg.0123456789abcdef.wasm`.
+fn hashed_file_name(file_name: &str, content: &[u8]) -> String {
+    let hash = format!("{:x}", Sha256::digest(content));
+    let hash = &hash[..HASH_LENGTH];
+
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{stem}.{hash}.{extension}"),
+        _ => format!("{file_name}.{hash}"),
+    }
+}
+
+/// Replace a reference to a renamed file in the given file.
+///
+/// A warning is printed if the reference can't be found, e.g. because a custom `index.html` loads
+/// the files differently.
+fn replace_reference(path: &Path, from: &str, to: &str) -> anyhow::Result<()> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    if !content.contains(from) {
+        println!(
+            "Could not find `{from}` in {}, it needs to be updated manually.",
+            path.display()
+        );
+        return Ok(());
+    }
+
+    fs::write(path, content.replace(from, to))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    Ok(())
+}
+
+/// The path of the meta file belonging to the asset, e.g. `icon.png.meta`.
+fn append_meta_extension(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".meta");
+    path.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_insert_hash_before_extension() {
+        let file_name = hashed_file_name("app_bg.wasm", b"");
+        assert_eq!(file_name, "app_bg.e3b0c44298fc1c14.wasm");
+    }
+
+    #[test]
+    fn should_append_hash_without_extension() {
+        assert_eq!(hashed_file_name("LICENSE", b""), "LICENSE.e3b0c44298fc1c14");
+        assert_eq!(hashed_file_name(".env", b""), ".env.e3b0c44298fc1c14");
+    }
+
+    #[test]
+    fn should_update_references_to_hashed_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_path = temp_dir.path();
+        fs::create_dir_all(base_path.join("build")).unwrap();
+        fs::create_dir_all(base_path.join("assets/textures")).unwrap();
+        fs::write(base_path.join("build/app_bg.wasm"), b"").unwrap();
+        fs::write(
+            base_path.join("build/app.js"),
+            "module_or_path = new URL('app_bg.wasm', import.meta.url);",
+        )
+        .unwrap();
+        fs::write(
+            base_path.join("index.html"),
+            r#"<script type="module">import init from "./build/app.js";</script>"#,
+        )
+        .unwrap();
+        fs::write(base_path.join("assets/textures/icon.png"), b"").unwrap();
+        fs::write(base_path.join("assets/textures/icon.png.meta"), b"()").unwrap();
+
+        hash_file_names(base_path, &["app"], true).unwrap();
+
+        let manifest: BTreeMap<String, String> =
+            serde_json::from_str(&fs::read_to_string(base_path.join(MANIFEST_FILE_NAME)).unwrap())
+                .unwrap();
+        let hashed_wasm_path = &manifest["build/app_bg.wasm"];
+        let hashed_js_path = &manifest["build/app.js"];
+        assert_eq!(hashed_wasm_path, "build/app_bg.e3b0c44298fc1c14.wasm");
+
+        let js = fs::read_to_string(base_path.join(hashed_js_path)).unwrap();
+        assert!(js.contains("new URL('app_bg.e3b0c44298fc1c14.wasm', import.meta.url)"));
+        let index = fs::read_to_string(base_path.join("index.html")).unwrap();
+        let hashed_js_import = format!("./{hashed_js_path}");
+        assert!(index.contains(&hashed_js_import));
+        assert!(!base_path.join("build/app.js").exists());
+        assert!(!base_path.join("build/app_bg.wasm").exists());
+
+        // The assets are still available under their original paths
+        let hashed_asset_path = &manifest["assets/textures/icon.png"];
+        assert_eq!(
+            hashed_asset_path,
+            "assets/textures/icon.e3b0c44298fc1c14.png"
+        );
+        assert!(base_path.join("assets/textures/icon.png").exists());
+        assert!(base_path.join("assets/textures/icon.png.meta").exists());
+        assert!(base_path
+            .join("assets/textures/icon.e3b0c44298fc1c14.png.meta")
+            .exists());
+    }
+}
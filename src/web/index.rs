@@ -0,0 +1,181 @@
+//! Rendering the `index.html` of web bundles.
+//!
+//! The default and custom `index.html` files can contain placeholders like `{{ bin_name }}`,
+//! which are replaced with values of the project:
+//!
+//! - `package_name` and `package_version`: The name and version of the package
+//! - `bin_name`: The name of the binary
+//! - `js_path` and `wasm_path`: The paths to the JS bindings and the Wasm binary
+//! - `wasm_size`: The size of the Wasm binary in bytes, e.g. to show the loading progress
+//! - `bindgen_target`: The kind of JS bindings, `web`, `no-modules` or `bundler`
+//! - `webgpu_js_path` and `webgpu_wasm_path`: The paths to the WebGPU build, if the app is built
+//!   for both rendering backends, otherwise the same as `js_path` and `wasm_path`
+//! - `title`: The title of the page, which defaults to the package name
+//!
+//! Additional values can be defined in the `template-values` table of the configuration, which
+//! can also override the values above. Unknown placeholders are kept as they are.
+//!
+//! The values are escaped for HTML, so characters like `<` or `"` in a title can't break the page.
+use std::collections::BTreeMap;
+
+use crate::{external_cli::wasm_bindgen::BindgenTarget, run::BinTarget};
+
+/// Determine the values for the placeholders of the `index.html` template.
+pub(crate) fn template_values(
+    bin_target: &BinTarget,
+    wasm_size: u64,
+    bindgen_target: BindgenTarget,
+    webgpu_out_name: Option<&str>,
+    custom_values: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    let webgpu_out_name = webgpu_out_name.unwrap_or(&bin_target.bin_name);
+    let mut values = BTreeMap::from([
+        ("package_name", bin_target.package_name.clone()),
+        ("package_version", bin_target.package_version.clone()),
+        ("bin_name", bin_target.bin_name.clone()),
+        ("js_path", format!("./build/{}.js", bin_target.bin_name)),
+        (
+            "wasm_path",
+            format!("./build/{}_bg.wasm", bin_target.bin_name),
+        ),
+        ("wasm_size", wasm_size.to_string()),
+        ("bindgen_target", bindgen_target.as_str().to_owned()),
+        ("webgpu_js_path", format!("./build/{webgpu_out_name}.js")),
+        (
+            "webgpu_wasm_path",
+            format!("./build/{webgpu_out_name}_bg.wasm"),
+        ),
+        ("title", bin_target.package_name.clone()),
+    ])
+    .into_iter()
+    .map(|(name, value)| (name.to_owned(), value))
+    .collect::<BTreeMap<_, _>>();
+
+    values.extend(
+        custom_values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone())),
+    );
+
+    values
+}
+
+/// Replace the placeholders in the HTML template with the given values, escaped for HTML.
+pub(crate) fn render_html(template: &str, values: &BTreeMap<String, String>) -> String {
+    let values = values
+        .iter()
+        .map(|(name, value)| (name.clone(), escape_html(value)))
+        .collect();
+    render(template, &values)
+}
+
+/// Replace the characters with a special meaning in HTML by their character references, so the
+/// value can be used in text and in quoted attribute values.
+pub(crate) fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+/// Replace the placeholders in the template with the given values, without escaping them.
+///
+/// Placeholders consist of a name enclosed in double curly braces, e.g. `{{ title }}`.
+/// The whitespace around the name is optional.
+pub(crate) fn render(template: &str, values: &BTreeMap<String, String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest
+            .find("}}")
+            .and_then(|end| Some((end, values.get(rest[2..end].trim())?)));
+
+        match value {
+            Some((end, value)) => {
+                output.push_str(value);
+                rest = &rest[end + 2..];
+            }
+            None => {
+                // Not a known placeholder, e.g. a template of a JS framework
+                output.push_str("{{");
+                rest = &rest[2..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+/// Insert the HTML snippet at the end of the head of the given HTML document.
+///
+/// If the document has no head, the snippet is placed at the beginning.
+pub(crate) fn insert_into_head(html: &str, snippet: &str) -> String {
+    match html.find("</head>") {
+        Some(index) => format!("{}{snippet}{}", &html[..index], &html[index..]),
+        None => format!("{snippet}{html}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn values() -> BTreeMap<String, String> {
+        let bin_target = BinTarget {
+            artifact_directory: PathBuf::from("target/wasm32-unknown-unknown/web"),
+            bin_name: "my_game".to_owned(),
+            package_name: "my-game".to_owned(),
+            package_version: "0.2.0".to_owned(),
+        };
+        let custom_values = BTreeMap::from([
+            ("title".to_owned(), "My Game".to_owned()),
+            ("theme_color".to_owned(), "#2b2c2f".to_owned()),
+        ]);
+
+        template_values(&bin_target, 1234, BindgenTarget::Web, None, &custom_values)
+    }
+
+    #[test]
+    fn should_replace_placeholders() {
+        assert_eq!(
+            render(
+                r#"<title>{{ title }} v{{package_version}}</title><script src="{{ js_path }}" data-size="{{ wasm_size }}"></script>"#,
+                &values()
+            ),
+            r#"<title>My Game v0.2.0</title><script src="./build/my_game.js" data-size="1234"></script>"#
+        );
+    }
+
+    #[test]
+    fn should_escape_html_values() {
+        let values = BTreeMap::from([("title".to_owned(), "Tom & Jerry's \"<Game>\"".to_owned())]);
+
+        assert_eq!(
+            render_html("<title>{{ title }}</title>", &values),
+            "<title>Tom &amp; Jerry&#39;s &quot;&lt;Game&gt;&quot;</title>"
+        );
+    }
+
+    #[test]
+    fn should_keep_unknown_placeholders() {
+        assert_eq!(
+            render("{{ unknown }} {{ theme_color }} {{", &values()),
+            "{{ unknown }} #2b2c2f {{"
+        );
+    }
+}
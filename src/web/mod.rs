@@ -1,6 +1,16 @@
 //! Utilities for building and running the app in the browser.
 
+pub(crate) mod archive;
+pub(crate) mod backend;
 pub(crate) mod bundle;
+pub(crate) mod compression;
+pub(crate) mod fingerprint;
+pub(crate) mod folders;
+pub(crate) mod gallery;
+pub(crate) mod hashing;
+pub(crate) mod index;
 pub(crate) mod profiles;
-#[cfg(feature = "wasm-opt")]
+pub(crate) mod pwa;
+pub(crate) mod size_report;
+pub(crate) mod threads;
 pub(crate) mod wasm_opt;
@@ -0,0 +1,220 @@
+//! Turning packed web bundles into progressive web apps (PWAs).
+//!
+//! A PWA can be installed like a native app and works offline after it has been loaded once.
+//! This requires a [web app manifest](https://developer.mozilla.org/en-US/docs/Web/Manifest),
+//! icons in several sizes and a service worker which caches the files of the app.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context as _;
+use image::imageops::FilterType;
+use serde_json::json;
+use sha2::{Digest as _, Sha256};
+
+use crate::{files, web::index};
+
+/// The name of the generated web app manifest.
+const MANIFEST_FILE_NAME: &str = "manifest.webmanifest";
+
+/// The name of the generated service worker.
+const SERVICE_WORKER_FILE_NAME: &str = "service-worker.js";
+
+/// The template of the service worker.
+const SERVICE_WORKER_TEMPLATE: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/assets/web/service_worker.js"
+));
+
+/// The sizes of the generated icons, which browsers require to install the app.
+const ICON_SIZES: [u32; 2] = [192, 512];
+
+/// The theme color, if none has been configured.
+///
+/// This matches the background of the default `index.html`.
+const DEFAULT_THEME_COLOR: &str = "#2b2c2f";
+
+/// Files of the bundle which are not requested by the browser.
+const EXCLUDED_FILES: &[&str] = &["_headers", SERVICE_WORKER_FILE_NAME];
+
+/// Options for bundling the app as progressive web app.
+#[derive(Debug, Clone, Default)]
+pub struct PwaOptions {
+    /// The image to create the icons from, ideally a square PNG file of at least 512x512 pixels.
+    pub icon_path: Option<PathBuf>,
+    /// The color of the title bar and the splash screen.
+    pub theme_color: Option<String>,
+}
+
+/// Add the web app manifest, icons and service worker to the packed bundle.
+///
+/// `index.html` is updated to reference the manifest and register the service worker.
+/// This has to happen after the file names have been hashed, so that the service worker caches
+/// the final files.
+pub(crate) fn create_pwa(base_path: &Path, name: &str, options: &PwaOptions) -> anyhow::Result<()> {
+    let icon_path = options.icon_path.as_deref().context(
+        "an icon is required to install the app, configure it with `--pwa-icon` or `pwa-icon`",
+    )?;
+    let icons = create_icons(base_path, icon_path)?;
+
+    let theme_color = options
+        .theme_color
+        .as_deref()
+        .unwrap_or(DEFAULT_THEME_COLOR);
+    let manifest = json!({
+        "name": name,
+        "short_name": name,
+        "start_url": ".",
+        "display": "fullscreen",
+        "background_color": theme_color,
+        "theme_color": theme_color,
+        "icons": icons,
+    });
+    fs::write(
+        base_path.join(MANIFEST_FILE_NAME),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .with_context(|| format!("failed to create {MANIFEST_FILE_NAME}"))?;
+
+    let index_path = base_path.join("index.html");
+    let index = fs::read_to_string(&index_path).context("failed to read index.html")?;
+    let theme_color = index::escape_html(theme_color);
+    let head = format!(
+        "<link rel=\"manifest\" href=\"{MANIFEST_FILE_NAME}\" />\n\
+        <meta name=\"theme-color\" content=\"{theme_color}\" />\n\
+        <script>\n\
+        if (\"serviceWorker\" in navigator) {{\n  \
+          navigator.serviceWorker.register(\"{SERVICE_WORKER_FILE_NAME}\");\n\
+        }}\n\
+        </script>\n"
+    );
+    fs::write(&index_path, index::insert_into_head(&index, &head))
+        .context("failed to update index.html")?;
+
+    // The service worker is created last, so that it caches the final versions of all files
+    fs::write(
+        base_path.join(SERVICE_WORKER_FILE_NAME),
+        service_worker(base_path)?,
+    )
+    .with_context(|| format!("failed to create {SERVICE_WORKER_FILE_NAME}"))?;
+
+    Ok(())
+}
+
+/// Create the icons in all required sizes, returning their entries for the manifest.
+fn create_icons(base_path: &Path, icon_path: &Path) -> anyhow::Result<Vec<serde_json::Value>> {
+    let icon = image::open(icon_path)
+        .with_context(|| format!("failed to read icon {}", icon_path.display()))?;
+
+    let icons_path = base_path.join("icons");
+    fs::create_dir_all(&icons_path)?;
+
+    ICON_SIZES
+        .iter()
+        .map(|&size| {
+            let file_name = format!("icon-{size}.png");
+            icon.resize_to_fill(size, size, FilterType::Lanczos3)
+                .save(icons_path.join(&file_name))
+                .with_context(|| format!("failed to create icon {file_name}"))?;
+
+            Ok(json!({
+                "src": format!("icons/{file_name}"),
+                "sizes": format!("{size}x{size}"),
+                "type": "image/png",
+                "purpose": "any",
+            }))
+        })
+        .collect()
+}
+
+/// Generate the service worker, which caches all files of the bundle.
+///
+/// The name of the cache contains a hash of the files, so that a new version of the app
+/// replaces the cached files.
+fn service_worker(base_path: &Path) -> anyhow::Result<String> {
+    let mut files = files::files_by_name(base_path)?;
+    files.retain(|url, _| !EXCLUDED_FILES.contains(&url.as_str()));
+
+    let mut hasher = Sha256::new();
+    for (url, path) in &files {
+        hasher.update(url.as_bytes());
+        hasher.update(fs::read(path)?);
+    }
+    let hash = format!("{:x}", hasher.finalize());
+
+    // The page itself is requested via the folder URL
+    let precache_urls: Vec<_> = ["./".to_owned()]
+        .into_iter()
+        .chain(files.keys().map(|url| format!("./{url}")))
+        .collect();
+
+    let values = BTreeMap::from([
+        ("cache_name".to_owned(), format!("bevy-app-{}", &hash[..16])),
+        (
+            "precache_urls".to_owned(),
+            serde_json::to_string_pretty(&precache_urls)?,
+        ),
+    ]);
+
+    Ok(index::render(SERVICE_WORKER_TEMPLATE, &values))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageBuffer, Rgba};
+
+    use super::*;
+
+    #[test]
+    fn should_create_manifest_icons_and_service_worker() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let base_path = temp_dir.path();
+        fs::create_dir_all(base_path.join("build")).unwrap();
+        fs::write(base_path.join("build/app_bg.wasm"), b"wasm").unwrap();
+        fs::write(base_path.join("_headers"), "/*\n").unwrap();
+        fs::write(
+            base_path.join("index.html"),
+            "<html><head></head><body></body></html>",
+        )
+        .unwrap();
+
+        let icon_path = base_path.join("source-icon.png");
+        ImageBuffer::from_pixel(64, 32, Rgba([255u8, 0, 0, 255]))
+            .save(&icon_path)
+            .unwrap();
+
+        let options = PwaOptions {
+            icon_path: Some(icon_path),
+            theme_color: None,
+        };
+        create_pwa(base_path, "My Game", &options).unwrap();
+
+        let icon = image::open(base_path.join("icons/icon-512.png")).unwrap();
+        assert_eq!((icon.width(), icon.height()), (512, 512));
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(base_path.join(MANIFEST_FILE_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(manifest["name"], "My Game");
+        assert_eq!(manifest["icons"][0]["src"], "icons/icon-192.png");
+
+        let index = fs::read_to_string(base_path.join("index.html")).unwrap();
+        assert!(index.contains("<link rel=\"manifest\" href=\"manifest.webmanifest\" />"));
+        assert!(index.find("serviceWorker").unwrap() < index.find("</head>").unwrap());
+
+        let service_worker = fs::read_to_string(base_path.join(SERVICE_WORKER_FILE_NAME)).unwrap();
+        assert!(service_worker.contains("\"./build/app_bg.wasm\""));
+        assert!(service_worker.contains("\"./icons/icon-192.png\""));
+        assert!(service_worker.contains("\"./manifest.webmanifest\""));
+        assert!(!service_worker.contains("_headers"));
+        assert!(!service_worker.contains("{{"));
+    }
+
+    #[test]
+    fn should_require_icon() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert!(create_pwa(temp_dir.path(), "My Game", &PwaOptions::default()).is_err());
+    }
+}
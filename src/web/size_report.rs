@@ -0,0 +1,381 @@
+//! Reporting what takes up the space in Wasm binaries.
+//!
+//! The binary size has a big influence on the loading times of web apps.
+//! The report breaks the size down by the sections of the Wasm module and by the functions and
+//! data segments, using the names from the `name` custom section.
+//! Function names are demangled and grouped by the crate they belong to.
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+use anyhow::Context as _;
+use serde::Serialize;
+use wasmparser::{KnownCustom, Name, Parser, Payload, TypeRef};
+
+use crate::web::archive::format_size;
+
+/// How many entries are printed for the largest crates and items.
+const PRINTED_ENTRIES: usize = 20;
+
+/// The group of items which don't belong to a crate, like functions generated by `wasm-bindgen`.
+const UNKNOWN_CRATE: &str = "[unknown]";
+
+/// The group of data segments.
+const DATA_CRATE: &str = "[data]";
+
+/// The size breakdown of a Wasm binary.
+#[derive(Debug, Serialize)]
+pub(crate) struct SizeReport {
+    /// The size of the whole binary in bytes.
+    pub total_size: u64,
+    /// Whether the binary contains the names of the functions.
+    ///
+    /// `wasm-opt` removes them, so all functions are unnamed for optimized builds.
+    pub has_names: bool,
+    /// The sizes of the sections, from largest to smallest.
+    pub sections: Vec<SizeEntry>,
+    /// The sizes of the crates, from largest to smallest.
+    pub crates: Vec<SizeEntry>,
+    /// The functions and data segments, from largest to smallest.
+    pub items: Vec<Item>,
+}
+
+/// A named part of the binary and its size.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub(crate) struct SizeEntry {
+    pub name: String,
+    pub size: u64,
+}
+
+/// A function or data segment in the binary.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub(crate) struct Item {
+    /// The demangled name of the item.
+    pub name: String,
+    /// The crate the item belongs to.
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    /// Either `function` or `data`.
+    pub kind: &'static str,
+    /// The size of the item in bytes.
+    pub size: u64,
+}
+
+impl SizeReport {
+    /// Analyze the Wasm binary at the given path.
+    pub(crate) fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_bytes(&bytes).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Analyze the given Wasm binary.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut sections = BTreeMap::<String, u64>::new();
+        let mut imported_functions = 0;
+        let mut function_sizes = Vec::new();
+        let mut data_sizes = Vec::new();
+        let mut function_names = HashMap::new();
+        let mut data_names = HashMap::new();
+
+        for payload in Parser::new(0).parse_all(bytes) {
+            let payload = payload?;
+
+            if let Some((id, range)) = payload.as_section() {
+                let name = match &payload {
+                    Payload::CustomSection(reader) => format!("custom: {}", reader.name()),
+                    _ => section_name(id).to_owned(),
+                };
+                *sections.entry(name).or_default() += range.len() as u64;
+            }
+
+            match payload {
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        if matches!(import?.ty, TypeRef::Func(_)) {
+                            imported_functions += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    function_sizes.push(body.range().len() as u64);
+                }
+                Payload::DataSection(reader) => {
+                    for data in reader {
+                        data_sizes.push(data?.range.len() as u64);
+                    }
+                }
+                Payload::CustomSection(reader) => {
+                    if let KnownCustom::Name(reader) = reader.as_known() {
+                        for name in reader {
+                            // A malformed name section shouldn't prevent the report
+                            let Ok(name) = name else {
+                                break;
+                            };
+                            match name {
+                                Name::Function(names) => {
+                                    for naming in names.into_iter().flatten() {
+                                        function_names.insert(naming.index, naming.name);
+                                    }
+                                }
+                                Name::Data(names) => {
+                                    for naming in names.into_iter().flatten() {
+                                        data_names.insert(naming.index, naming.name);
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let has_names = !function_names.is_empty();
+
+        let functions = function_sizes.into_iter().enumerate().map(|(i, size)| {
+            // Imported functions come first in the index space
+            let index = imported_functions + i as u32;
+            let name = function_names
+                .get(&index)
+                .map_or_else(|| format!("function[{index}]"), |name| demangle(name));
+            Item {
+                crate_name: crate_name(&name).to_owned(),
+                name,
+                kind: "function",
+                size,
+            }
+        });
+        let data = data_sizes.into_iter().enumerate().map(|(i, size)| {
+            let index = i as u32;
+            Item {
+                name: data_names
+                    .get(&index)
+                    .map_or_else(|| format!("data[{index}]"), |name| (*name).to_owned()),
+                crate_name: DATA_CRATE.to_owned(),
+                kind: "data",
+                size,
+            }
+        });
+        let mut items: Vec<_> = functions.chain(data).collect();
+        items.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+        let mut crates = BTreeMap::<String, u64>::new();
+        for item in &items {
+            *crates.entry(item.crate_name.clone()).or_default() += item.size;
+        }
+
+        Ok(Self {
+            total_size: bytes.len() as u64,
+            has_names,
+            sections: sorted_entries(sections),
+            crates: sorted_entries(crates),
+            items,
+        })
+    }
+
+    /// Print the sections and the largest crates and items.
+    pub(crate) fn print(&self) {
+        println!("Total size: {}", format_size(self.total_size));
+
+        println!("\nSections:");
+        self.print_entries(&self.sections);
+
+        if !self.has_names {
+            println!(
+                "\nThe binary contains no function names, so they can't be attributed to crates."
+            );
+            println!("Function names are removed by wasm-opt, which runs for release builds.");
+            return;
+        }
+
+        println!("\nLargest crates:");
+        self.print_entries(&self.crates[..self.crates.len().min(PRINTED_ENTRIES)]);
+
+        println!("\nLargest functions and data:");
+        for item in self.items.iter().take(PRINTED_ENTRIES) {
+            println!(
+                "  {:>10}  {:>5.1}%  {} ({})",
+                format_size(item.size),
+                self.percentage(item.size),
+                item.name,
+                item.crate_name
+            );
+        }
+    }
+
+    /// Write the full report as JSON file.
+    pub(crate) fn write_json(&self, path: &Path) -> anyhow::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    fn print_entries(&self, entries: &[SizeEntry]) {
+        for entry in entries {
+            println!(
+                "  {:>10}  {:>5.1}%  {}",
+                format_size(entry.size),
+                self.percentage(entry.size),
+                entry.name
+            );
+        }
+    }
+
+    fn percentage(&self, size: u64) -> f64 {
+        size as f64 / self.total_size.max(1) as f64 * 100.
+    }
+}
+
+/// The name of the section with the given ID.
+fn section_name(id: u8) -> &'static str {
+    match id {
+        1 => "type",
+        2 => "import",
+        3 => "function",
+        4 => "table",
+        5 => "memory",
+        6 => "global",
+        7 => "export",
+        8 => "start",
+        9 => "element",
+        10 => "code",
+        11 => "data",
+        12 => "data count",
+        13 => "tag",
+        _ => "unknown",
+    }
+}
+
+/// Demangle the Rust symbol name, leaving other names as they are.
+fn demangle(name: &str) -> String {
+    match rustc_demangle::try_demangle(name) {
+        // The alternate format omits the hash
+        Ok(demangled) => format!("{demangled:#}"),
+        Err(_) => name.to_owned(),
+    }
+}
+
+/// Determine the crate of the demangled function name.
+///
+/// For trait implementations like `<alloc::vec::Vec<T> as core::ops::Drop>::drop`, this is the
+/// crate of the implementing type.
+fn crate_name(name: &str) -> &str {
+    let name = name.trim_start_matches('<');
+    match name.find("::") {
+        Some(end) if !name[..end].contains([' ', '<', '(']) => &name[..end],
+        _ => UNKNOWN_CRATE,
+    }
+}
+
+/// Sort the sizes from largest to smallest.
+fn sorted_entries(sizes: BTreeMap<String, u64>) -> Vec<SizeEntry> {
+    let mut entries: Vec<_> = sizes
+        .into_iter()
+        .map(|(name, size)| SizeEntry { name, size })
+        .collect();
+    entries.sort_by(|a, b| b.size.cmp(&a.size));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_encoder::{
+        CodeSection, CustomSection, DataSection, EntityType, Function, FunctionSection,
+        ImportSection, Instruction, Module, NameMap, NameSection, TypeSection,
+    };
+
+    use super::*;
+
+    fn module() -> Vec<u8> {
+        let mut module = Module::new();
+
+        let mut types = TypeSection::new();
+        types.ty().function([], []);
+        module.section(&types);
+
+        let mut imports = ImportSection::new();
+        imports.import("wbg", "__wbg_log", EntityType::Function(0));
+        module.section(&imports);
+
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+        functions.function(0);
+        module.section(&functions);
+
+        let mut code = CodeSection::new();
+        let mut small = Function::new([]);
+        small.instruction(&Instruction::End);
+        code.function(&small);
+        let mut large = Function::new([]);
+        for _ in 0..20 {
+            large.instruction(&Instruction::Nop);
+        }
+        large.instruction(&Instruction::End);
+        code.function(&large);
+        module.section(&code);
+
+        let mut data = DataSection::new();
+        data.passive([0; 10]);
+        module.section(&data);
+
+        let mut names = NameSection::new();
+        let mut function_names = NameMap::new();
+        function_names.append(0, "__wbg_log");
+        function_names.append(1, "__wbindgen_malloc");
+        function_names.append(2, "_ZN8bevy_ecs5world5World5spawn17h0123456789abcdefE");
+        names.functions(&function_names);
+        let mut data_names = NameMap::new();
+        data_names.append(0, ".rodata");
+        names.data(&data_names);
+        module.section(&names);
+
+        module.section(&CustomSection {
+            name: "producers".into(),
+            data: [0; 4].as_slice().into(),
+        });
+
+        module.finish()
+    }
+
+    #[test]
+    fn should_break_down_size() {
+        let bytes = module();
+        let report = SizeReport::from_bytes(&bytes).unwrap();
+
+        assert_eq!(report.total_size, bytes.len() as u64);
+        assert!(report.has_names);
+        assert!(report.sections.iter().any(|section| section.name == "code"));
+        assert!(report
+            .sections
+            .iter()
+            .any(|section| section.name == "custom: producers"));
+
+        let names: Vec<_> = report
+            .items
+            .iter()
+            .map(|item| (item.name.as_str(), item.crate_name.as_str(), item.kind))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("bevy_ecs::world::World::spawn", "bevy_ecs", "function"),
+                (".rodata", DATA_CRATE, "data"),
+                ("__wbindgen_malloc", UNKNOWN_CRATE, "function"),
+            ]
+        );
+        assert_eq!(report.crates[0].name, "bevy_ecs");
+    }
+
+    #[test]
+    fn should_determine_crate_names() {
+        assert_eq!(crate_name("bevy_ecs::world::World::spawn"), "bevy_ecs");
+        assert_eq!(
+            crate_name("<alloc::vec::Vec<T> as core::ops::drop::Drop>::drop"),
+            "alloc"
+        );
+        assert_eq!(crate_name("__wbindgen_malloc"), UNKNOWN_CRATE);
+        assert_eq!(crate_name("<[T] as core::fmt::Debug>::fmt"), UNKNOWN_CRATE);
+    }
+}
@@ -0,0 +1,99 @@
+//! Multithreading support for web builds.
+//!
+//! Wasm threads are based on shared memory, which requires
+//! - the `atomics` and `bulk-memory` target features,
+//! - the standard library to be recompiled with these features, which is only possible on nightly,
+//! - the page to be [cross-origin isolated](https://developer.mozilla.org/en-US/docs/Web/API/Window/crossOriginIsolated).
+use std::{env, process::Command};
+
+use crate::external_cli::rustup;
+
+/// The `rustc` flags enabling the target features needed for threads.
+///
+/// The linker needs to create a shared memory and export the symbols wasm-bindgen uses to set up
+/// the threads.
+///
+/// Each entry is passed to `rustc` as a single argument.
+pub(crate) const RUSTFLAGS: &[&str] = &[
+    "-Ctarget-feature=+atomics,+bulk-memory,+mutable-globals",
+    "-Clink-arg=--shared-memory",
+    "-Clink-arg=--max-memory=1073741824",
+    "-Clink-arg=--import-memory",
+    "-Clink-arg=--export=__wasm_init_tls",
+    "-Clink-arg=--export=__tls_size",
+    "-Clink-arg=--export=__tls_align",
+    "-Clink-arg=--export=__tls_base",
+    "-Clink-arg=--export=__heap_base",
+];
+
+/// The unstable `cargo` argument to recompile the standard library with the target features.
+pub(crate) const BUILD_STD_ARG: &str = "-Zbuild-std=std,panic_abort";
+
+/// The headers making the page cross-origin isolated, which enables shared memory.
+pub(crate) const CROSS_ORIGIN_ISOLATION_HEADERS: [(&str, &str); 2] = [
+    ("Cross-Origin-Opener-Policy", "same-origin"),
+    ("Cross-Origin-Embedder-Policy", "require-corp"),
+];
+
+/// The toolchain to compile with if the active one is not a nightly toolchain.
+const NIGHTLY_TOOLCHAIN: &str = "nightly";
+
+/// Make sure that a nightly toolchain with the sources of the standard library is available.
+///
+/// Returns the toolchain to compile with, if the active toolchain can't be used.
+pub(crate) fn ensure_setup(skip_prompts: bool) -> anyhow::Result<Option<&'static str>> {
+    if is_nightly_active() {
+        rustup::install_component_if_needed("rust-src", None, skip_prompts)?;
+        return Ok(None);
+    }
+
+    println!("Multithreading requires a nightly toolchain, using `{NIGHTLY_TOOLCHAIN}`.");
+    rustup::install_toolchain_if_needed(NIGHTLY_TOOLCHAIN, skip_prompts)?;
+    rustup::install_component_if_needed("rust-src", Some(NIGHTLY_TOOLCHAIN), skip_prompts)?;
+
+    Ok(Some(NIGHTLY_TOOLCHAIN))
+}
+
+/// Determine if the active toolchain is a nightly toolchain.
+fn is_nightly_active() -> bool {
+    let rustc = env::var_os("RUSTC").unwrap_or("rustc".into());
+    let Ok(output) = Command::new(rustc).arg("--version").output() else {
+        return false;
+    };
+
+    // Example output: rustc 1.86.0-nightly (a580b5c37 2025-01-08)
+    let version = String::from_utf8_lossy(&output.stdout);
+    version.contains("-nightly") || version.contains("-dev")
+}
+
+/// The contents of a `_headers` file for the packed bundle.
+///
+/// Many static hosting providers, like Netlify and Cloudflare Pages, use it to set the headers of
+/// the served files.
+pub(crate) fn headers_file() -> String {
+    let mut contents = "/*\n".to_owned();
+
+    for (name, value) in CROSS_ORIGIN_ISOLATION_HEADERS {
+        contents.push_str(&format!("  {name}: {value}\n"));
+    }
+
+    contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_not_split_rustflags() {
+        assert!(RUSTFLAGS.iter().all(|flag| !flag.contains(' ')));
+    }
+
+    #[test]
+    fn should_apply_headers_to_all_files() {
+        assert_eq!(
+            headers_file(),
+            "/*\n  Cross-Origin-Opener-Policy: same-origin\n  Cross-Origin-Embedder-Policy: require-corp\n"
+        );
+    }
+}
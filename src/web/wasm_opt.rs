@@ -1,29 +1,118 @@
-use std::{fs, path::Path, time::Instant};
+use std::{ffi::OsString, fs, path::Path, time::Instant};
 
 use anyhow::Context as _;
+use clap::ValueEnum;
+use serde::Deserialize;
 
 use crate::run::BinTarget;
 
-/// Optimize the binary with wasm-opt.
-pub(crate) fn optimize_bin(bin_target: &BinTarget) -> anyhow::Result<()> {
+/// The program used to optimize the binary, if the `wasm-opt` feature is disabled.
+#[cfg(not(feature = "wasm-opt"))]
+const PROGRAM: &str = "wasm-opt";
+
+/// How wasm-opt optimizes the binary, corresponding to its `-O` flags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+pub enum OptimizationLevel {
+    /// Quick optimizations (`-O1`).
+    #[value(name = "1")]
+    #[serde(rename = "1")]
+    O1,
+    /// Most optimizations (`-O2`).
+    #[value(name = "2")]
+    #[serde(rename = "2")]
+    O2,
+    /// Spend more time optimizing (`-O3`).
+    #[value(name = "3")]
+    #[serde(rename = "3")]
+    O3,
+    /// Also flatten the code, which can help in some cases (`-O4`).
+    #[value(name = "4")]
+    #[serde(rename = "4")]
+    O4,
+    /// Optimize for size (`-Os`).
+    #[default]
+    #[value(name = "s")]
+    #[serde(rename = "s")]
+    S,
+    /// Optimize aggressively for size (`-Oz`).
+    #[value(name = "z")]
+    #[serde(rename = "z")]
+    Z,
+}
+
+impl OptimizationLevel {
+    /// The wasm-opt flag for this level.
+    fn flag(self) -> &'static str {
+        match self {
+            Self::O1 => "-O1",
+            Self::O2 => "-O2",
+            Self::O3 => "-O3",
+            Self::O4 => "-O4",
+            Self::S => "-Os",
+            Self::Z => "-Oz",
+        }
+    }
+}
+
+/// The options to run wasm-opt with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct WasmOptOptions {
+    pub level: OptimizationLevel,
+    /// Additional arguments, like passes (`--strip-debug`) or features (`--enable-simd`).
+    pub args: Vec<String>,
+}
+
+impl WasmOptOptions {
+    /// The command line arguments to optimize the binary at the given path in place.
+    fn command_args(&self, path: &Path) -> Vec<OsString> {
+        let mut args = vec![
+            path.as_os_str().to_owned(),
+            "-o".into(),
+            path.as_os_str().to_owned(),
+            self.level.flag().into(),
+        ];
+        args.extend(self.args.iter().map(OsString::from));
+        args
+    }
+}
+
+/// Optimize the binary of the bindings named `out_name` with wasm-opt.
+///
+/// Without the `wasm-opt` feature, the `wasm-opt` program is used if it is installed.
+/// Returns `false` if the binary could not be optimized because the program is missing.
+pub(crate) fn optimize_bin(
+    bin_target: &BinTarget,
+    out_name: &str,
+    options: &WasmOptOptions,
+) -> anyhow::Result<bool> {
     let wasm_path = bin_target
         .artifact_directory
-        .clone()
-        .join(format!("{}_bg.wasm", bin_target.bin_name));
+        .join(format!("{out_name}_bg.wasm"));
 
-    optimize_path(&wasm_path)
+    optimize_path(&wasm_path, options)
 }
 
 /// Optimize the Wasm binary at the given path with wasm-opt.
-fn optimize_path(path: &Path) -> anyhow::Result<()> {
+fn optimize_path(path: &Path, options: &WasmOptOptions) -> anyhow::Result<bool> {
+    #[cfg(not(feature = "wasm-opt"))]
+    if std::process::Command::new(PROGRAM)
+        .arg("--version")
+        .output()
+        .is_err()
+    {
+        println!(
+            "Skipping optimization, because `{PROGRAM}` is not installed. \
+            Install it or the CLI with the `wasm-opt` feature to optimize the binary."
+        );
+        return Ok(false);
+    }
+
     println!("Optimizing with wasm-opt...");
 
     let start = Instant::now();
     let size_before = fs::metadata(path)?.len();
 
-    wasm_opt::OptimizationOptions::new_optimize_for_size()
-        .run(path, path)
-        .context("failed to optimize with wasm-opt")?;
+    run(&options.command_args(path))?;
 
     let size_after = fs::metadata(path)?.len();
     let size_reduction = 1. - (size_after as f32) / (size_before as f32);
@@ -34,5 +123,51 @@ fn optimize_path(path: &Path) -> anyhow::Result<()> {
         size_reduction * 100.
     );
 
+    Ok(true)
+}
+
+/// Run the wasm-opt library, which interprets the arguments like the program does.
+#[cfg(feature = "wasm-opt")]
+fn run(args: &[OsString]) -> anyhow::Result<()> {
+    let mut command = wasm_opt::integration::Command::new("wasm-opt");
+    command.args(args);
+    wasm_opt::integration::run_from_command_args(command)
+        .context("failed to optimize with wasm-opt")
+}
+
+/// Run the installed wasm-opt program.
+#[cfg(not(feature = "wasm-opt"))]
+fn run(args: &[OsString]) -> anyhow::Result<()> {
+    use crate::external_cli::CommandHelpers as _;
+
+    std::process::Command::new(PROGRAM)
+        .args(args)
+        .ensure_status()
+        .context("failed to optimize with wasm-opt")?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_optimize_in_place_with_extra_args() {
+        let options = WasmOptOptions {
+            level: OptimizationLevel::Z,
+            args: vec!["--enable-simd".to_owned(), "--strip-debug".to_owned()],
+        };
+
+        assert_eq!(
+            options.command_args(Path::new("app_bg.wasm")),
+            [
+                "app_bg.wasm",
+                "-o",
+                "app_bg.wasm",
+                "-Oz",
+                "--enable-simd",
+                "--strip-debug"
+            ]
+        );
+    }
+}